@@ -8,10 +8,11 @@
 
 mod vulkan;
 use anyhow::Result;
-use vulkan::structures::App;
+use log::*;
+use vulkan::{config::Config, debug_mode::DebugMode, golden::run_golden_tests, offscreen::save_png, structures::App};
 use winit::{
     dpi::LogicalSize,
-    event::{ElementState, Event, WindowEvent},
+    event::{ElementState, Event, MouseButton, MouseScrollDelta, WindowEvent},
     event_loop::EventLoop,
     keyboard::{KeyCode, PhysicalKey},
     window::WindowBuilder,
@@ -21,17 +22,36 @@ use winit::{
 fn main() -> Result<()> {
     pretty_env_logger::init();
 
+    let config = Config::from_args()?;
+
+    // Golden Images
+
+    if let Some(directory) = &config.golden {
+        return unsafe { run_golden_tests(&config, directory) };
+    }
+
+    // Headless
+
+    if config.headless {
+        let mut app = unsafe { App::create_headless(&config)? };
+        let pixels = unsafe { app.render_offscreen() };
+        unsafe { app.destroy(); }
+        save_png(&config.output, config.width, config.height, &pixels?)?;
+        info!("Wrote offscreen frame to `{}`.", config.output.display());
+        return Ok(());
+    }
+
     // Window
 
     let event_loop = EventLoop::new().unwrap();
     let window = WindowBuilder::new()
         .with_title("Vulkan Tutorial (Rust)")
-        .with_inner_size(LogicalSize::new(1024, 768))
+        .with_inner_size(LogicalSize::new(config.width, config.height))
         .build(&event_loop).unwrap();
 
     // App
 
-    let mut app = unsafe { App::create(&window).unwrap() };
+    let mut app = unsafe { App::create(&window, &config)? };
     let mut minimized = false;
     event_loop.run(move |event, elwt| {
         match event {
@@ -58,13 +78,44 @@ fn main() -> Result<()> {
                 }
                 // Handle keyboard events.
                 WindowEvent::KeyboardInput { event, .. } => {
-                    if event.state == ElementState::Pressed {
-                        match event.physical_key {
-                            PhysicalKey::Code(KeyCode::ArrowLeft) if app.models > 1 => app.models -= 1,
-                            PhysicalKey::Code(KeyCode::ArrowRight) if app.models < 4 => app.models += 1,
-                            _ => { }
-                        }
+                    let PhysicalKey::Code(key) = event.physical_key else { return };
+                    let pressed = event.state == ElementState::Pressed;
+                    if app.camera.set_key(key, pressed) || !pressed || event.repeat {
+                        return;
                     }
+
+                    match key {
+                        KeyCode::ArrowLeft if app.models > 1 => app.models -= 1,
+                        KeyCode::ArrowRight if app.models < 4 => app.models += 1,
+                        KeyCode::KeyC => app.camera.toggle_mode(),
+                        KeyCode::KeyM => if let Err(e) = unsafe { app.cycle_msaa_samples() } {
+                            error!("Failed to switch the MSAA sample count: {}", e);
+                        },
+                        KeyCode::KeyV => if let Err(e) = unsafe { app.cycle_vsync(&window) } {
+                            error!("Failed to switch the vsync mode: {}", e);
+                        },
+                        KeyCode::Digit1 => app.set_debug_mode(DebugMode::Lit),
+                        KeyCode::Digit2 => app.set_debug_mode(DebugMode::Wireframe),
+                        KeyCode::Digit3 => app.set_debug_mode(DebugMode::Normals),
+                        KeyCode::Digit4 => app.set_debug_mode(DebugMode::TexCoords),
+                        KeyCode::Digit5 => app.set_debug_mode(DebugMode::VertexColor),
+                        KeyCode::Digit6 => app.set_debug_mode(DebugMode::Depth),
+                        KeyCode::Digit7 => app.set_debug_mode(DebugMode::MipLevel),
+                        _ => { }
+                    }
+                }
+                // Turn the camera while the left mouse button is held.
+                WindowEvent::MouseInput { state, button: MouseButton::Left, .. } => {
+                    app.camera.set_dragging(state == ElementState::Pressed);
+                }
+                WindowEvent::CursorMoved { position, .. } => app.camera.cursor_moved(position.x, position.y),
+                // Zoom the camera.
+                WindowEvent::MouseWheel { delta, .. } => {
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(_, y) => y,
+                        MouseScrollDelta::PixelDelta(position) => position.y as f32 / 40.0,
+                    };
+                    app.camera.scroll(lines);
                 }
                 _ => {}
             }
@@ -0,0 +1,54 @@
+#version 450
+
+#include "output.glsl"
+
+// The debug mode (see `DebugMode::builder`).
+layout(constant_id = 0) const int MODE = 0;
+// The depth range of the projection.
+layout(constant_id = 1) const float NEAR = 0.1;
+layout(constant_id = 2) const float FAR = 10.0;
+
+const int MODE_NORMALS = 0;
+const int MODE_TEX_COORDS = 1;
+const int MODE_VERTEX_COLOR = 2;
+const int MODE_DEPTH = 3;
+const int MODE_MIP_LEVEL = 4;
+
+layout(binding = 1) uniform sampler2D texSampler;
+
+layout(location = 0) in vec3 fragColor;
+layout(location = 1) in vec2 fragTexCoord;
+layout(location = 2) in vec3 fragNormal;
+layout(location = 3) in vec4 fragTangent;
+
+layout(location = 0) out vec4 outColor;
+
+const vec3 MIP_COLORS[6] = vec3[](
+    vec3(1.0, 0.0, 0.0),
+    vec3(1.0, 0.5, 0.0),
+    vec3(1.0, 1.0, 0.0),
+    vec3(0.0, 1.0, 0.0),
+    vec3(0.0, 0.5, 1.0),
+    vec3(0.5, 0.0, 1.0)
+);
+
+void main() {
+    vec3 color;
+    if (MODE == MODE_NORMALS) {
+        color = normalize(fragNormal) * 0.5 + 0.5;
+    } else if (MODE == MODE_TEX_COORDS) {
+        color = vec3(fract(fragTexCoord), 0.0);
+    } else if (MODE == MODE_VERTEX_COLOR) {
+        color = fragColor;
+    } else if (MODE == MODE_DEPTH) {
+        // Inverts the [0, 1] depth produced by the corrected projection (see `update_uniform_buffer`).
+        float distance = NEAR * FAR / (FAR - gl_FragCoord.z * (FAR - NEAR));
+        color = vec3(1.0 - (distance - NEAR) / (FAR - NEAR));
+    } else {
+        float level = textureQueryLod(texSampler, fragTexCoord).x;
+        int index = min(int(level), 4);
+        color = mix(MIP_COLORS[index], MIP_COLORS[index + 1], clamp(level - float(index), 0.0, 1.0));
+    }
+
+    outColor = vec4(encode_output(color), 1.0);
+}
@@ -0,0 +1,61 @@
+// Converts the linear Rec.709 colors produced by our shaders to the color space of the swapchain
+// (see `OutputColorSpace`).
+
+// The output color space.
+layout(constant_id = 10) const int OUTPUT = 0;
+// The luminance (in nits) of SDR white on HDR outputs.
+layout(constant_id = 11) const float PAPER_WHITE = 203.0;
+
+const int OUTPUT_SDR = 0;
+const int OUTPUT_HDR10 = 1;
+const int OUTPUT_EXTENDED_SRGB_LINEAR = 2;
+const int OUTPUT_DISPLAY_P3 = 3;
+
+// The luminance (in nits) that highlights are compressed towards on HDR outputs.
+const float PEAK_LUMINANCE = 1000.0;
+// The luminance (in nits) of 1.0 in extended sRGB linear (scRGB).
+const float SCRGB_WHITE = 80.0;
+
+// Rec.709 to Rec.2020 and Display P3 primaries (column-major).
+const mat3 REC709_TO_REC2020 = mat3(
+    0.6274, 0.0691, 0.0164,
+    0.3293, 0.9195, 0.0880,
+    0.0433, 0.0114, 0.8956
+);
+const mat3 REC709_TO_DISPLAY_P3 = mat3(
+    0.8225, 0.0332, 0.0171,
+    0.1774, 0.9669, 0.0724,
+    0.0000, 0.0000, 0.9108
+);
+
+// Compresses colors brighter than a knee so they approach (but never exceed) `peak`, preserving their hue.
+vec3 tonemap(vec3 color, float peak) {
+    float knee = 0.75 * peak;
+    float brightest = max(max(color.r, color.g), color.b);
+    if (brightest <= knee) {
+        return color;
+    }
+
+    float t = (brightest - knee) / (peak - knee);
+    return color * ((knee + (peak - knee) * t / (1.0 + t)) / brightest);
+}
+
+// The ST 2084 (PQ) inverse EOTF of luminances in nits.
+vec3 pq(vec3 nits) {
+    vec3 y = pow(clamp(nits / 10000.0, 0.0, 1.0), vec3(0.1593017578125));
+    return pow((0.8359375 + 18.8515625 * y) / (1.0 + 18.6875 * y), vec3(78.84375));
+}
+
+vec3 encode_output(vec3 color) {
+    if (OUTPUT == OUTPUT_HDR10) {
+        vec3 white = tonemap(max(REC709_TO_REC2020 * color, 0.0), PEAK_LUMINANCE / PAPER_WHITE);
+        return pq(white * PAPER_WHITE);
+    } else if (OUTPUT == OUTPUT_EXTENDED_SRGB_LINEAR) {
+        return tonemap(max(color, 0.0), PEAK_LUMINANCE / PAPER_WHITE) * (PAPER_WHITE / SCRGB_WHITE);
+    } else if (OUTPUT == OUTPUT_DISPLAY_P3) {
+        // Rec.709 lies within Display P3, so SDR content needs no tonemapping.
+        return clamp(REC709_TO_DISPLAY_P3 * color, 0.0, 1.0);
+    } else {
+        return color;
+    }
+}
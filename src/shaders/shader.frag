@@ -1,5 +1,7 @@
 #version 450
 
+#include "output.glsl"
+
 layout(binding = 1) uniform sampler2D texSampler;
 
 layout(push_constant) uniform PushConstants {
@@ -8,9 +10,18 @@ layout(push_constant) uniform PushConstants {
 
 layout(location = 0) in vec3 fragColor;
 layout(location = 1) in vec2 fragTexCoord;
+layout(location = 2) in vec3 fragNormal;
+layout(location = 3) in vec4 fragTangent;
 
 layout(location = 0) out vec4 outColor;
 
+const vec3 LIGHT_DIRECTION = normalize(vec3(1.0, 0.5, 1.0));
+const float AMBIENT = 0.35;
+
 void main() {
-    outColor = vec4(texture(texSampler, fragTexCoord).rgb, pcs.opacity);
+    float diffuse = max(dot(normalize(fragNormal), LIGHT_DIRECTION), 0.0);
+    // The vertex color carries the diffuse color of the material.
+    vec3 albedo = texture(texSampler, fragTexCoord).rgb * fragColor;
+    vec3 color = albedo * (AMBIENT + (1.0 - AMBIENT) * diffuse);
+    outColor = vec4(encode_output(color), pcs.opacity);
 }
\ No newline at end of file
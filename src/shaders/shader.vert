@@ -12,12 +12,18 @@ layout(push_constant) uniform PushConstants {
 layout(location = 0) in vec3 inPosition;
 layout(location = 1) in vec3 inColor;
 layout(location = 2) in vec2 inTexCoord;
+layout(location = 3) in vec3 inNormal;
+layout(location = 4) in vec4 inTangent;
 
 layout(location = 0) out vec3 fragColor;
 layout(location = 1) out vec2 fragTexCoord;
+layout(location = 2) out vec3 fragNormal;
+layout(location = 3) out vec4 fragTangent;
 
 void main() {
     gl_Position = ubo.proj * ubo.view * pcs.model * vec4(inPosition, 1.0);
     fragColor = inColor;
     fragTexCoord = inTexCoord;
+    fragNormal = mat3(pcs.model) * inNormal;
+    fragTangent = vec4(mat3(pcs.model) * inTangent.xyz, inTangent.w);
 }
\ No newline at end of file
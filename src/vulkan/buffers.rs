@@ -63,9 +63,20 @@ pub unsafe fn create_vertex_buffer(instance: &Instance, device: &Device, data: &
 }
 
 pub unsafe fn create_index_buffer(instance: &Instance, device: &Device, data: &mut AppData) -> Result<()> {
+    // Index Type
+
+    // Primitive restart is disabled, so every 16-bit value (including `0xFFFF`) is a valid index.
+    let (index_type, index_size) = if data.vertices.len() <= u16::MAX as usize + 1 {
+        (vk::IndexType::UINT16, size_of::<u16>())
+    } else {
+        (vk::IndexType::UINT32, size_of::<u32>())
+    };
+
+    data.index_type = index_type;
+
     // Create (staging)
 
-    let size = (size_of::<u32>() * data.indices.len()) as u64;
+    let size = (index_size * data.indices.len()) as u64;
 
     let (staging_buffer, staging_buffer_memory) = create_buffer(
         instance,
@@ -83,7 +94,12 @@ pub unsafe fn create_index_buffer(instance: &Instance, device: &Device, data: &m
         .map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())
         .unwrap();
 
-    memcpy(data.indices.as_ptr(), memory.cast(), data.indices.len());
+    if index_type == vk::IndexType::UINT16 {
+        let indices = data.indices.iter().map(|i| *i as u16).collect::<Vec<_>>();
+        memcpy(indices.as_ptr(), memory.cast(), indices.len());
+    } else {
+        memcpy(data.indices.as_ptr(), memory.cast(), data.indices.len());
+    }
 
     device.unmap_memory(staging_buffer_memory);
 
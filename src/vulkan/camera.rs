@@ -0,0 +1,194 @@
+use super::{
+    config::{CameraMode, Config},
+    constants::{Mat4, Vec3},
+};
+use cgmath::{point3, vec3, Deg, InnerSpace, Point3, Zero};
+use std::{collections::HashSet, f32::consts::FRAC_PI_2};
+use winit::keyboard::KeyCode;
+
+//================================================
+// Camera
+//================================================
+
+// The radians the camera turns per pixel the mouse is dragged.
+const ROTATE_SPEED: f32 = 0.005;
+// The factor the orbit distance is multiplied by per line scrolled.
+const ZOOM_FACTOR: f32 = 0.9;
+// The degrees the free-fly field of view narrows per line scrolled.
+const FOV_STEP: f32 = 2.5;
+// How close the camera can look straight up or down (to keep the view matrix well-defined).
+const MAX_PITCH: f32 = FRAC_PI_2 - 0.01;
+
+// A camera that orbits a target or flies freely, with Z up.
+//
+// The camera always looks along the direction given by its yaw and pitch. In orbit mode, it's placed `distance` away
+// from the target along that direction; in free-fly mode it's placed at `position`.
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub mode: CameraMode,
+    pub target: Point3<f32>,
+    pub distance: f32,
+    pub position: Point3<f32>,
+    pub yaw: f32,
+    pub pitch: f32,
+    // The vertical field of view (in degrees) and the depth range of the projection.
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+    // The units moved per second while a movement key is held.
+    pub speed: f32,
+    // Input
+    dragging: bool,
+    cursor: Option<(f64, f64)>,
+    keys: HashSet<KeyCode>,
+}
+
+impl Camera {
+    pub fn new(config: &Config) -> Self {
+        // Looks at the origin from (6, 0, 2).
+        let eye = vec3(6.0, 0.0, 2.0);
+        Self {
+            mode: config.camera,
+            target: point3(0.0, 0.0, 0.0),
+            distance: eye.magnitude(),
+            position: point3(eye.x, eye.y, eye.z),
+            yaw: (-eye.y).atan2(-eye.x),
+            pitch: (-eye.z / eye.magnitude()).asin(),
+            fov: config.fov,
+            near: config.near,
+            far: config.far,
+            speed: 2.0,
+            dragging: false,
+            cursor: None,
+            keys: HashSet::new(),
+        }
+    }
+
+    // The unit vector the camera looks along.
+    pub fn forward(&self) -> Vec3 {
+        vec3(
+            self.pitch.cos() * self.yaw.cos(),
+            self.pitch.cos() * self.yaw.sin(),
+            self.pitch.sin(),
+        )
+    }
+
+    // The position of the camera.
+    pub fn eye(&self) -> Point3<f32> {
+        match self.mode {
+            CameraMode::Orbit => self.target - self.forward() * self.distance,
+            CameraMode::FreeFly => self.position,
+        }
+    }
+
+    pub fn view(&self) -> Mat4 {
+        let eye = self.eye();
+        Mat4::look_at_rh(eye, eye + self.forward(), vec3(0.0, 0.0, 1.0))
+    }
+
+    // The OpenGL-style perspective projection (see `update_uniform_buffer` for the Vulkan correction).
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        cgmath::perspective(Deg(self.fov), aspect, self.near, self.far)
+    }
+
+    // Switches modes without moving the camera.
+    pub fn set_mode(&mut self, mode: CameraMode) {
+        if mode == self.mode {
+            return;
+        }
+
+        match mode {
+            CameraMode::Orbit => self.target = self.position + self.forward() * self.distance,
+            CameraMode::FreeFly => self.position = self.eye(),
+        }
+
+        self.mode = mode;
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.set_mode(match self.mode {
+            CameraMode::Orbit => CameraMode::FreeFly,
+            CameraMode::FreeFly => CameraMode::Orbit,
+        });
+    }
+
+    //================================================
+    // Input
+    //================================================
+
+    pub fn set_dragging(&mut self, dragging: bool) {
+        self.dragging = dragging;
+    }
+
+    // Turns the camera by the distance the cursor moved while dragging.
+    pub fn cursor_moved(&mut self, x: f64, y: f64) {
+        if let Some((last_x, last_y)) = self.cursor {
+            if self.dragging {
+                self.yaw -= (x - last_x) as f32 * ROTATE_SPEED;
+                self.pitch = (self.pitch - (y - last_y) as f32 * ROTATE_SPEED).clamp(-MAX_PITCH, MAX_PITCH);
+            }
+        }
+
+        self.cursor = Some((x, y));
+    }
+
+    // Moves towards the target (orbit) or narrows the field of view (free-fly) for positive lines.
+    pub fn scroll(&mut self, lines: f32) {
+        match self.mode {
+            CameraMode::Orbit => {
+                self.distance = (self.distance * ZOOM_FACTOR.powf(lines))
+                    .min(self.far * 0.9)
+                    .max(self.near * 2.0);
+            }
+            CameraMode::FreeFly => self.fov = (self.fov - lines * FOV_STEP).clamp(10.0, 120.0),
+        }
+    }
+
+    // Records whether a movement key is held; returns whether the key is a movement key.
+    pub fn set_key(&mut self, key: KeyCode, pressed: bool) -> bool {
+        if !matches!(
+            key,
+            KeyCode::KeyW | KeyCode::KeyA | KeyCode::KeyS | KeyCode::KeyD | KeyCode::KeyQ | KeyCode::KeyE
+        ) {
+            return false;
+        }
+
+        if pressed {
+            self.keys.insert(key);
+        } else {
+            self.keys.remove(&key);
+        }
+
+        true
+    }
+
+    // Moves the camera (free-fly) or its target (orbit) by the movement keys held over the elapsed seconds.
+    pub fn update(&mut self, delta: f32) {
+        let forward = self.forward();
+        let right = forward.cross(vec3(0.0, 0.0, 1.0)).normalize();
+        let up = vec3(0.0, 0.0, 1.0);
+
+        let mut direction = Vec3::zero();
+        for key in &self.keys {
+            direction += match key {
+                KeyCode::KeyW => forward,
+                KeyCode::KeyS => -forward,
+                KeyCode::KeyD => right,
+                KeyCode::KeyA => -right,
+                KeyCode::KeyE => up,
+                KeyCode::KeyQ => -up,
+                _ => Vec3::zero(),
+            };
+        }
+
+        if direction.is_zero() {
+            return;
+        }
+
+        let offset = direction.normalize() * self.speed * delta;
+        match self.mode {
+            CameraMode::Orbit => self.target += offset,
+            CameraMode::FreeFly => self.position += offset,
+        }
+    }
+}
@@ -10,6 +10,14 @@ use vulkanalia::prelude::v1_0::*;
 //================================================
 
 pub unsafe fn create_color_objects(instance: &Instance, device: &Device, data: &mut AppData) -> Result<()> {
+    // Without multisampling, we render directly into the swapchain images.
+    if data.msaa_samples == vk::SampleCountFlags::_1 {
+        data.color_image = vk::Image::null();
+        data.color_image_memory = vk::DeviceMemory::null();
+        data.color_image_view = vk::ImageView::null();
+        return Ok(());
+    }
+
     // Image + Image Memory
 
     let (color_image, color_image_memory) = create_image(
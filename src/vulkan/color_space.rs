@@ -0,0 +1,62 @@
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Color Space
+//================================================
+
+// The specialization constants of the output transform (see `src/shaders/output.glsl`).
+pub const OUTPUT_CONSTANT_ID: u32 = 10;
+pub const PAPER_WHITE_CONSTANT_ID: u32 = 11;
+
+// The color space and encoding frames are presented in.
+//
+// Shaders produce linear Rec.709 colors, which are converted to the output color space by the output transform. The
+// discriminants are the `OUTPUT_*` values of the output transform's specialization constant.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum OutputColorSpace {
+    // sRGB, encoded by the swapchain format.
+    #[default]
+    Sdr = 0,
+    // Rec.2020 primaries with the ST 2084 (PQ) transfer function.
+    Hdr10 = 1,
+    // Linear Rec.709 primaries extended beyond [0, 1] (scRGB, where 1.0 is 80 nits).
+    ExtendedSrgbLinear = 2,
+    // Display P3 primaries with the sRGB transfer function, encoded by the swapchain format.
+    DisplayP3 = 3,
+}
+
+impl OutputColorSpace {
+    // The surface color space swapchains presenting in this color space are created with.
+    pub fn surface_color_space(self) -> vk::ColorSpaceKHR {
+        match self {
+            Self::Sdr => vk::ColorSpaceKHR::SRGB_NONLINEAR,
+            Self::Hdr10 => vk::ColorSpaceKHR::HDR10_ST2084_EXT,
+            Self::ExtendedSrgbLinear => vk::ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+            Self::DisplayP3 => vk::ColorSpaceKHR::DISPLAY_P3_NONLINEAR_EXT,
+        }
+    }
+
+    // The swapchain formats that can hold this color space, in order of preference.
+    pub fn formats(self) -> &'static [vk::Format] {
+        match self {
+            Self::Sdr => &[vk::Format::B8G8R8A8_SRGB],
+            Self::Hdr10 => &[
+                vk::Format::A2B10G10R10_UNORM_PACK32,
+                vk::Format::A2R10G10B10_UNORM_PACK32,
+                vk::Format::R16G16B16A16_SFLOAT,
+            ],
+            Self::ExtendedSrgbLinear => &[vk::Format::R16G16B16A16_SFLOAT],
+            Self::DisplayP3 => &[vk::Format::B8G8R8A8_SRGB, vk::Format::R8G8B8A8_SRGB],
+        }
+    }
+
+    // Finds a surface format for this color space among those supported by a surface.
+    pub fn find_surface_format(self, formats: &[vk::SurfaceFormatKHR]) -> Option<vk::SurfaceFormatKHR> {
+        self.formats().iter().find_map(|format| {
+            formats
+                .iter()
+                .cloned()
+                .find(|f| f.format == *format && f.color_space == self.surface_color_space())
+        })
+    }
+}
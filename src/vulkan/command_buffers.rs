@@ -7,6 +7,8 @@ use vulkanalia::prelude::v1_0::*;
 //================================================
 
 pub unsafe fn create_command_buffers(device: &Device, data: &mut AppData) -> Result<()> {
+    data.command_buffers.clear();
+
     let num_images = data.swapchain_images.len();
     for image_index in 0..num_images {
         let allocate_info = vk::CommandBufferAllocateInfo::builder()
@@ -1,4 +1,4 @@
-use super::structures::{AppData, QueueFamilyIndices};
+use super::structures::AppData;
 use anyhow::Result;
 use vulkanalia::prelude::v1_0::*;
 
@@ -9,25 +9,31 @@ use vulkanalia::prelude::v1_0::*;
 pub unsafe fn create_command_pools(instance: &Instance, device: &Device, data: &mut AppData) -> Result<()> {
     // Global
 
-    data.command_pool = create_command_pool(instance, device, data).unwrap();
+    data.command_pool = create_command_pool(device, data.queue_families.graphics).unwrap();
+    data.transfer_command_pool = create_command_pool(device, data.queue_families.transfer).unwrap();
 
     // Per-framebuffer
 
-    let num_images = data.swapchain_images.len();
-    for _ in 0..num_images {
-        let command_pool = create_command_pool(instance, device, data).unwrap();
-        data.command_pools.push(command_pool);
-    }
+    create_image_command_pools(device, data)
+}
+
+// Creates a command pool for each swapchain image, which are recreated with the swapchain since the number of images
+// may change.
+pub unsafe fn create_image_command_pools(device: &Device, data: &mut AppData) -> Result<()> {
+    data.command_pools = data
+        .swapchain_images
+        .iter()
+        .map(|_| create_command_pool(device, data.queue_families.graphics))
+        .collect::<Result<Vec<_>>>()
+        .unwrap();
 
     Ok(())
 }
 
-pub unsafe fn create_command_pool(instance: &Instance, device: &Device, data: &mut AppData) -> Result<vk::CommandPool> {
-    let indices = QueueFamilyIndices::get(instance, data, data.physical_device).unwrap();
-
+pub unsafe fn create_command_pool(device: &Device, queue_family_index: u32) -> Result<vk::CommandPool> {
     let info = vk::CommandPoolCreateInfo::builder()
         .flags(vk::CommandPoolCreateFlags::TRANSIENT)
-        .queue_family_index(indices.graphics);
+        .queue_family_index(queue_family_index);
 
     Ok(device.create_command_pool(&info, None).unwrap())
 }
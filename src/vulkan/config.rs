@@ -0,0 +1,351 @@
+use super::color_space::OutputColorSpace;
+use anyhow::{anyhow, Result};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+//================================================
+// Config
+//================================================
+
+// The model loaded when no other model is configured (relative to the working directory, like every path).
+pub const DEFAULT_MODEL: &str = "src/resources/viking_room.obj";
+// The texture loaded when no other texture is configured.
+pub const DEFAULT_TEXTURE: &str = "src/resources/viking_room.png";
+
+// The directory the GLSL shaders are compiled from (and watched for changes).
+pub const DEFAULT_SHADERS: &str = "src/shaders";
+
+// The directory of the reference images the golden image scenes are compared against.
+pub const DEFAULT_GOLDEN: &str = "src/resources/golden";
+
+// The directory (in the user's cache directory) and the file compiled pipelines are cached in by default.
+const CACHE_DIRECTORY: &str = "vulkanalia-tutorial";
+const PIPELINE_CACHE_FILE: &str = "pipeline_cache.bin";
+
+// The environment variable that selects the physical device (overridden by the `device` setting).
+const DEVICE_VAR: &str = "VULKAN_DEVICE";
+
+// The settings that are switched on by passing them without a value.
+const FLAGS: &[&str] = &["headless", "golden-update"];
+
+// The settings whose value can be left out on the command line to use their default.
+const OPTIONAL_VALUES: &[&str] = &["golden"];
+
+// The settings whose values are paths (relative to the config file when read from one).
+const PATHS: &[&str] = &["output", "golden", "model", "texture", "shaders", "pipeline-cache"];
+
+// How normals are generated for models that don't provide them.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Normals {
+    // Averaged across the faces sharing a position.
+    #[default]
+    Smooth,
+    // Perpendicular to each face.
+    Flat,
+}
+
+// How the camera is moved.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum CameraMode {
+    // Turns around a target, zooming towards it.
+    #[default]
+    Orbit,
+    // Moves freely through the scene.
+    FreeFly,
+}
+
+// Whether presenting waits for the display's vertical blank (see `get_swapchain_present_mode`).
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum VSync {
+    // Always waits (FIFO).
+    #[default]
+    On,
+    // Never waits (IMMEDIATE or else MAILBOX), so frames may tear.
+    Off,
+    // Waits unless a frame is late (FIFO_RELAXED).
+    Adaptive,
+}
+
+// The settings our Vulkan app is started with.
+//
+// Every setting can be passed as a command-line argument (`--model room.obj`) or as a line in a config file passed
+// with `--config` (`model = room.obj`). Command-line arguments override the config file. Paths are relative to the
+// working directory, or to the config file they are read from.
+#[derive(Clone, Debug)]
+pub struct Config {
+    // Whether to render offscreen without a window or surface.
+    pub headless: bool,
+    // The PNG file offscreen frames are written to.
+    pub output: PathBuf,
+    // The size of the window or offscreen image.
+    pub width: u32,
+    pub height: u32,
+    // The directory of reference images to compare rendered frames against.
+    pub golden: Option<PathBuf>,
+    // Whether to overwrite the reference images instead of comparing against them.
+    pub golden_update: bool,
+    // The largest per-channel difference still considered a match.
+    pub golden_tolerance: u8,
+    // The assets rendered by our Vulkan app.
+    pub model: PathBuf,
+    pub texture: PathBuf,
+    pub normals: Normals,
+    pub shaders: PathBuf,
+    // The file compiled pipelines are cached in between runs.
+    pub pipeline_cache: PathBuf,
+    // The MSAA sample count (the highest supported, up to 8, if `None`) and the minimum fraction of samples shaded
+    // individually (no sample shading if zero).
+    pub msaa: Option<u32>,
+    pub sample_shading: f32,
+    // The vertical sync mode and the frame rate that frames are capped at when not synchronized (uncapped if `None`).
+    pub vsync: VSync,
+    pub fps_limit: Option<u32>,
+    // The color space frames are presented in (if the surface supports it) and the luminance (in nits) of SDR white
+    // on HDR outputs.
+    pub color_space: OutputColorSpace,
+    pub paper_white: f32,
+    // The physical device to use, by index or (part of its) name (the highest scoring device if `None`).
+    pub device: Option<String>,
+    // The initial camera mode and the vertical field of view (in degrees) and depth range of the projection.
+    pub camera: CameraMode,
+    pub fov: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            headless: false,
+            output: PathBuf::from("frame.png"),
+            width: 1024,
+            height: 768,
+            golden: None,
+            golden_update: false,
+            golden_tolerance: 2,
+            model: PathBuf::from(DEFAULT_MODEL),
+            texture: PathBuf::from(DEFAULT_TEXTURE),
+            normals: Normals::default(),
+            shaders: PathBuf::from(DEFAULT_SHADERS),
+            pipeline_cache: default_pipeline_cache(),
+            msaa: None,
+            sample_shading: 0.2,
+            vsync: VSync::default(),
+            fps_limit: None,
+            color_space: OutputColorSpace::default(),
+            paper_white: 203.0,
+            device: None,
+            camera: CameraMode::default(),
+            fov: 45.0,
+            near: 0.1,
+            far: 10.0,
+        }
+    }
+}
+
+impl Config {
+    // Parses the command-line arguments passed to our Vulkan app.
+    pub fn from_args() -> Result<Self> {
+        let mut config = Self {
+            device: env::var(DEVICE_VAR).ok().filter(|d| !d.is_empty()),
+            ..Self::default()
+        };
+
+        let mut settings = vec![];
+        let mut args = env::args().skip(1).peekable();
+        while let Some(arg) = args.next() {
+            let key = arg
+                .strip_prefix("--")
+                .ok_or_else(|| anyhow!("Unknown argument `{}`.", arg))?;
+
+            let value = if FLAGS.contains(&key) {
+                None
+            } else if OPTIONAL_VALUES.contains(&key) {
+                args.next_if(|a| !a.starts_with("--"))
+            } else {
+                Some(args.next().ok_or_else(|| anyhow!("Missing value for `{}`.", arg))?)
+            };
+
+            if key == "config" {
+                config.load(value.as_deref().unwrap_or_default())?;
+            } else {
+                settings.push((key.to_string(), value));
+            }
+        }
+
+        for (key, value) in settings {
+            config.set(&key, value.as_deref())?;
+        }
+
+        if !(config.near > 0.0 && config.far > config.near) {
+            return Err(anyhow!(
+                "Invalid depth range {}..{} (expected `0 < near < far`).",
+                config.near,
+                config.far
+            ));
+        }
+
+        Ok(config)
+    }
+
+    // Applies the `key = value` lines of a config file.
+    pub fn load(&mut self, path: &str) -> Result<()> {
+        let contents = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read config `{}`: {}", path, e))?;
+        let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+
+        for (number, line) in contents.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (key, value) = line.split_once('=').ok_or_else(|| {
+                anyhow!(
+                    "Invalid line {} in config `{}` (expected `key = value`).",
+                    number + 1,
+                    path
+                )
+            })?;
+
+            let (key, value) = (key.trim(), value.trim());
+            if PATHS.contains(&key) {
+                let value = directory.join(value);
+                self.set(key, Some(&value.to_string_lossy()))?;
+            } else {
+                self.set(key, Some(value))?;
+            }
+        }
+
+        Ok(())
+    }
+
+    // Applies a single setting; flags without a value are switched on.
+    pub fn set(&mut self, key: &str, value: Option<&str>) -> Result<()> {
+        let flag = || value.map_or(Ok(true), |v| v.parse::<bool>());
+        let value_or = |default| value.unwrap_or(default);
+        let value = || value.ok_or_else(|| anyhow!("Missing value for `{}`.", key));
+
+        match key {
+            "headless" => self.headless = flag()?,
+            "output" => self.output = PathBuf::from(value()?),
+            "size" => (self.width, self.height) = parse_size(value()?)?,
+            "golden" => self.golden = Some(PathBuf::from(value_or(DEFAULT_GOLDEN))),
+            "golden-update" => self.golden_update = flag()?,
+            "golden-tolerance" => self.golden_tolerance = value()?.parse()?,
+            "model" => self.model = PathBuf::from(value()?),
+            "texture" => self.texture = PathBuf::from(value()?),
+            "normals" => {
+                self.normals = match value()? {
+                    "smooth" => Normals::Smooth,
+                    "flat" => Normals::Flat,
+                    other => return Err(anyhow!("Invalid normals `{}` (expected `smooth` or `flat`).", other)),
+                }
+            }
+            "shaders" => self.shaders = PathBuf::from(value()?),
+            "pipeline-cache" => self.pipeline_cache = PathBuf::from(value()?),
+            "msaa" => {
+                self.msaa = match value()?.parse()? {
+                    samples @ (1 | 2 | 4 | 8) => Some(samples),
+                    other => {
+                        return Err(anyhow!(
+                            "Invalid MSAA sample count `{}` (expected 1, 2, 4 or 8).",
+                            other
+                        ))
+                    }
+                }
+            }
+            "sample-shading" => {
+                self.sample_shading = value()?.parse()?;
+                if !(0.0..=1.0).contains(&self.sample_shading) {
+                    return Err(anyhow!(
+                        "Invalid sample shading `{}` (expected 0 to 1).",
+                        self.sample_shading
+                    ));
+                }
+            }
+            "vsync" => {
+                self.vsync = match value()? {
+                    "on" => VSync::On,
+                    "off" => VSync::Off,
+                    "adaptive" => VSync::Adaptive,
+                    other => {
+                        return Err(anyhow!(
+                            "Invalid vsync `{}` (expected `on`, `off` or `adaptive`).",
+                            other
+                        ))
+                    }
+                }
+            }
+            // A limit of zero removes the cap.
+            "fps-limit" => self.fps_limit = Some(value()?.parse()?).filter(|f| *f > 0),
+            "color-space" => {
+                self.color_space = match value()? {
+                    "sdr" => OutputColorSpace::Sdr,
+                    "hdr10" => OutputColorSpace::Hdr10,
+                    "scrgb" => OutputColorSpace::ExtendedSrgbLinear,
+                    "display-p3" => OutputColorSpace::DisplayP3,
+                    other => {
+                        return Err(anyhow!(
+                            "Invalid color space `{}` (expected `sdr`, `hdr10`, `scrgb` or `display-p3`).",
+                            other
+                        ))
+                    }
+                }
+            }
+            "paper-white" => {
+                self.paper_white = value()?.parse()?;
+                if !(self.paper_white.is_finite() && self.paper_white > 0.0) {
+                    return Err(anyhow!(
+                        "Invalid paper white `{}` (expected a positive luminance in nits).",
+                        self.paper_white
+                    ));
+                }
+            }
+            "device" => self.device = Some(value()?.to_string()),
+            "camera" => {
+                self.camera = match value()? {
+                    "orbit" => CameraMode::Orbit,
+                    "fly" => CameraMode::FreeFly,
+                    other => return Err(anyhow!("Invalid camera `{}` (expected `orbit` or `fly`).", other)),
+                }
+            }
+            "fov" => self.fov = value()?.parse()?,
+            "near" => self.near = value()?.parse()?,
+            "far" => self.far = value()?.parse()?,
+            _ => return Err(anyhow!("Unknown setting `{}`.", key)),
+        }
+
+        Ok(())
+    }
+}
+
+// The file compiled pipelines are cached in by default, in the user's cache directory or else next to the executable
+// (not in the working directory, which may change between runs).
+fn default_pipeline_cache() -> PathBuf {
+    let var = |name: &str| env::var_os(name).filter(|v| !v.is_empty()).map(PathBuf::from);
+
+    let directory = var("XDG_CACHE_HOME")
+        .or_else(|| var("LOCALAPPDATA"))
+        .or_else(|| var("HOME").map(|h| h.join(".cache")))
+        .map(|d| d.join(CACHE_DIRECTORY))
+        .or_else(|| env::current_exe().ok()?.parent().map(|d| d.to_path_buf()))
+        .unwrap_or_default();
+
+    directory.join(PIPELINE_CACHE_FILE)
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32)> {
+    let (width, height) = value
+        .split_once('x')
+        .ok_or_else(|| anyhow!("Invalid size `{}` (expected `WIDTHxHEIGHT`).", value))?;
+
+    let width = width.parse::<u32>()?;
+    let height = height.parse::<u32>()?;
+    if width == 0 || height == 0 {
+        return Err(anyhow!("Invalid size `{}` (must not be zero).", value));
+    }
+
+    Ok((width, height))
+}
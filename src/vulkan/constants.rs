@@ -10,9 +10,13 @@ pub const DEVICE_EXTENSIONS: &[vk::ExtensionName] = &[vk::KHR_SWAPCHAIN_EXTENSIO
 // The Vulkan SDK version that started requiring the portability subset extension for macOS.
 pub const PORTABILITY_MACOS_VERSION: Version = Version::new(1, 3, 216);
 
+// The highest MSAA sample count used, even if the device supports more (which mostly costs performance).
+pub const MAX_MSAA_SAMPLES: vk::SampleCountFlags = vk::SampleCountFlags::_8;
+
 // The maximum number of frames that can be processed concurrently.
 pub const MAX_FRAMES_IN_FLIGHT: usize = 2;
 
 pub type Vec2 = cgmath::Vector2<f32>;
 pub type Vec3 = cgmath::Vector3<f32>;
+pub type Vec4 = cgmath::Vector4<f32>;
 pub type Mat4 = cgmath::Matrix4<f32>;
@@ -0,0 +1,70 @@
+use super::{
+    pipeline_builder::{BlendPreset, PipelineBuilder},
+    shaders::{DEBUG_FRAGMENT_SHADER, VERTEX_SHADER},
+    structures::Vertex,
+};
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Debug Mode
+//================================================
+
+// How our models are shaded, each mode drawn with its own pipeline variant.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum DebugMode {
+    // Lit and textured (the regular pipeline).
+    #[default]
+    Lit,
+    // Lit and textured triangle edges (requires the `fill_mode_non_solid` feature).
+    Wireframe,
+    // World space normals mapped from [-1, 1] to [0, 1].
+    Normals,
+    // Texture coordinates as red and green.
+    TexCoords,
+    // Vertex colors (the diffuse colors of the materials), unlit and untextured.
+    VertexColor,
+    // Linear depth between the near (white) and far (black) planes.
+    Depth,
+    // The sampled mip level of the texture, one color per level.
+    MipLevel,
+}
+
+impl DebugMode {
+    pub const ALL: [Self; 7] = [
+        Self::Lit,
+        Self::Wireframe,
+        Self::Normals,
+        Self::TexCoords,
+        Self::VertexColor,
+        Self::Depth,
+        Self::MipLevel,
+    ];
+
+    // The settings of the pipeline variant for this mode, for a projection with the given depth range.
+    pub fn builder(self, near: f32, far: f32) -> PipelineBuilder {
+        // The value of the `MODE` specialization constant in the debug fragment shader.
+        let mode = match self {
+            Self::Lit => return PipelineBuilder::model().vertex::<Vertex>(),
+            Self::Wireframe => {
+                return PipelineBuilder::model()
+                    .vertex::<Vertex>()
+                    .polygon_mode(vk::PolygonMode::LINE)
+                    .cull_mode(vk::CullModeFlags::NONE)
+                    .blend(BlendPreset::Opaque)
+            }
+            Self::Normals => 0,
+            Self::TexCoords => 1,
+            Self::VertexColor => 2,
+            Self::Depth => 3,
+            Self::MipLevel => 4,
+        };
+
+        PipelineBuilder::new()
+            .shader(VERTEX_SHADER)
+            .shader(DEBUG_FRAGMENT_SHADER)
+            .vertex::<Vertex>()
+            .specialize(0, mode)
+            .specialize(1, near.to_bits())
+            .specialize(2, far.to_bits())
+    }
+}
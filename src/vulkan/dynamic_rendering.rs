@@ -0,0 +1,210 @@
+use super::structures::AppData;
+use vulkanalia::{prelude::v1_3::*, vk::KhrDynamicRenderingExtension};
+
+//================================================
+// Dynamic Rendering
+//================================================
+
+// How a device provides dynamic rendering (rendering without render pass and framebuffer objects).
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum DynamicRendering {
+    // Vulkan 1.3.
+    Core,
+    // `VK_KHR_dynamic_rendering` (on Vulkan 1.1 or 1.2).
+    Extension,
+}
+
+// The device extensions required for `VK_KHR_dynamic_rendering` on Vulkan 1.1.
+pub const DYNAMIC_RENDERING_EXTENSIONS: &[vk::ExtensionName] = &[
+    vk::KHR_DYNAMIC_RENDERING_EXTENSION.name,
+    vk::KHR_DEPTH_STENCIL_RESOLVE_EXTENSION.name,
+    vk::KHR_CREATE_RENDERPASS2_EXTENSION.name,
+];
+
+// Begins rendering into the attachments for a swapchain image, resolving the multisampled color image into it
+// (or rendering into it directly without multisampling).
+//
+// The attachments are transitioned from whatever the previous frame left them in, as a render pass would.
+pub unsafe fn begin_rendering(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    image_index: usize,
+    color_clear_value: vk::ClearValue,
+    depth_clear_value: vk::ClearValue,
+) {
+    // Layouts
+
+    let color_stage = vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT;
+    let depth_stage = vk::PipelineStageFlags::EARLY_FRAGMENT_TESTS | vk::PipelineStageFlags::LATE_FRAGMENT_TESTS;
+
+    let color_barrier = |image| {
+        image_barrier(
+            image,
+            vk::ImageAspectFlags::COLOR,
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+            vk::AccessFlags::COLOR_ATTACHMENT_WRITE,
+        )
+    };
+
+    let depth_barrier = image_barrier(
+        data.depth_image,
+        get_depth_aspects(data.depth_format),
+        vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+        vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+        vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ | vk::AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+    );
+
+    let resolve = data.msaa_samples != vk::SampleCountFlags::_1;
+
+    let mut barriers = vec![color_barrier(data.swapchain_images[image_index]), depth_barrier];
+    if resolve {
+        barriers.push(color_barrier(data.color_image));
+    }
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        color_stage | depth_stage,
+        color_stage | depth_stage,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &barriers,
+    );
+
+    // Attachments
+
+    let color_attachment = if resolve {
+        vk::RenderingAttachmentInfo::builder()
+            .image_view(data.color_image_view)
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .resolve_mode(vk::ResolveModeFlags::AVERAGE)
+            .resolve_image_view(data.swapchain_image_views[image_index])
+            .resolve_image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::DONT_CARE)
+            .clear_value(color_clear_value)
+    } else {
+        vk::RenderingAttachmentInfo::builder()
+            .image_view(data.swapchain_image_views[image_index])
+            .image_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .load_op(vk::AttachmentLoadOp::CLEAR)
+            .store_op(vk::AttachmentStoreOp::STORE)
+            .clear_value(color_clear_value)
+    };
+
+    let depth_attachment = vk::RenderingAttachmentInfo::builder()
+        .image_view(data.depth_image_view)
+        .image_layout(vk::ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
+        .load_op(vk::AttachmentLoadOp::CLEAR)
+        .store_op(vk::AttachmentStoreOp::DONT_CARE)
+        .clear_value(depth_clear_value);
+
+    // Begin
+
+    let render_area = vk::Rect2D::builder()
+        .offset(vk::Offset2D::default())
+        .extent(data.swapchain_extent);
+
+    let color_attachments = &[color_attachment];
+    let info = vk::RenderingInfo::builder()
+        .flags(vk::RenderingFlags::CONTENTS_SECONDARY_COMMAND_BUFFERS)
+        .render_area(render_area)
+        .layer_count(1)
+        .color_attachments(color_attachments)
+        .depth_attachment(&depth_attachment);
+
+    match data.features.dynamic_rendering {
+        Some(DynamicRendering::Core) => device.cmd_begin_rendering(command_buffer, &info),
+        Some(DynamicRendering::Extension) => device.cmd_begin_rendering_khr(command_buffer, &info),
+        // Callers fall back to render passes if dynamic rendering is not supported.
+        None => unreachable!("Dynamic rendering is not supported by this device."),
+    }
+}
+
+// Ends rendering into the attachments for a swapchain image, leaving it ready to be presented (or copied out of).
+pub unsafe fn end_rendering(device: &Device, data: &AppData, command_buffer: vk::CommandBuffer, image_index: usize) {
+    match data.features.dynamic_rendering {
+        Some(DynamicRendering::Core) => device.cmd_end_rendering(command_buffer),
+        Some(DynamicRendering::Extension) => device.cmd_end_rendering_khr(command_buffer),
+        // Callers fall back to render passes if dynamic rendering is not supported.
+        None => unreachable!("Dynamic rendering is not supported by this device."),
+    }
+
+    // Layout
+
+    let (layout, access, stage) = if data.headless {
+        (
+            vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+            vk::AccessFlags::TRANSFER_READ,
+            vk::PipelineStageFlags::TRANSFER,
+        )
+    } else {
+        (
+            vk::ImageLayout::PRESENT_SRC_KHR,
+            vk::AccessFlags::empty(),
+            vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        )
+    };
+
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+        .new_layout(layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(data.swapchain_images[image_index])
+        .subresource_range(subresource_range(vk::ImageAspectFlags::COLOR))
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_access_mask(access);
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        stage,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[barrier],
+    );
+}
+
+// The aspects of a depth attachment image, which include stencil for combined depth/stencil formats.
+pub fn get_depth_aspects(format: vk::Format) -> vk::ImageAspectFlags {
+    match format {
+        vk::Format::D32_SFLOAT_S8_UINT | vk::Format::D24_UNORM_S8_UINT | vk::Format::D16_UNORM_S8_UINT => {
+            vk::ImageAspectFlags::DEPTH | vk::ImageAspectFlags::STENCIL
+        }
+        _ => vk::ImageAspectFlags::DEPTH,
+    }
+}
+
+// A barrier transitioning an attachment whose previous contents are discarded.
+fn image_barrier(
+    image: vk::Image,
+    aspects: vk::ImageAspectFlags,
+    layout: vk::ImageLayout,
+    src_access_mask: vk::AccessFlags,
+    dst_access_mask: vk::AccessFlags,
+) -> vk::ImageMemoryBarrier {
+    vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::UNDEFINED)
+        .new_layout(layout)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(image)
+        .subresource_range(subresource_range(aspects))
+        .src_access_mask(src_access_mask)
+        .dst_access_mask(dst_access_mask)
+        .build()
+}
+
+fn subresource_range(aspects: vk::ImageAspectFlags) -> vk::ImageSubresourceRange {
+    vk::ImageSubresourceRange::builder()
+        .aspect_mask(aspects)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+        .build()
+}
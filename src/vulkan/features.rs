@@ -0,0 +1,155 @@
+use super::{
+    dynamic_rendering::{DynamicRendering, DYNAMIC_RENDERING_EXTENSIONS},
+    structures::AppData,
+};
+use std::collections::HashSet;
+use vulkanalia::{prelude::v1_3::*, Version};
+
+//================================================
+// Features
+//================================================
+
+// The highest Vulkan version our app can use.
+pub const MAX_API_VERSION: Version = Version::new(1, 3, 0);
+
+// The Vulkan version used for a device and the optional features enabled for it.
+//
+// Subsystems check these instead of querying the device so they only use what was actually enabled.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeviceFeatures {
+    // The lower of the instance's and the device's Vulkan versions (see `vk::make_version`).
+    pub api_version: u32,
+    // Vulkan 1.0
+    pub sampler_anisotropy: bool,
+    pub sample_rate_shading: bool,
+    pub fill_mode_non_solid: bool,
+    // Vulkan 1.1
+    pub shader_draw_parameters: bool,
+    // Vulkan 1.2
+    pub timeline_semaphore: bool,
+    // Non-uniform indexing of partially bound, runtime sized arrays of sampled images.
+    pub descriptor_indexing: bool,
+    // Vulkan 1.3
+    pub synchronization2: bool,
+    pub dynamic_rendering: Option<DynamicRendering>,
+}
+
+impl DeviceFeatures {
+    // The names of the optional features that are supported (or enabled).
+    pub fn names(&self) -> Vec<&'static str> {
+        [
+            (self.sampler_anisotropy, "samplerAnisotropy"),
+            (self.sample_rate_shading, "sampleRateShading"),
+            (self.fill_mode_non_solid, "fillModeNonSolid"),
+            (self.shader_draw_parameters, "shaderDrawParameters"),
+            (self.timeline_semaphore, "timelineSemaphore"),
+            (self.descriptor_indexing, "descriptorIndexing"),
+            (self.synchronization2, "synchronization2"),
+            (
+                self.dynamic_rendering == Some(DynamicRendering::Core),
+                "dynamicRendering",
+            ),
+            (
+                self.dynamic_rendering == Some(DynamicRendering::Extension),
+                "dynamicRendering (VK_KHR_dynamic_rendering)",
+            ),
+        ]
+        .into_iter()
+        .filter(|(supported, _)| *supported)
+        .map(|(_, name)| name)
+        .collect()
+    }
+}
+
+// Finds the Vulkan version to create our instance for, the highest supported by both the loader and our app.
+pub unsafe fn get_instance_version(entry: &Entry) -> u32 {
+    let loader = entry.version().unwrap();
+    let version = if loader < MAX_API_VERSION {
+        loader
+    } else {
+        MAX_API_VERSION
+    };
+    vk::make_version(version.major, version.minor, 0)
+}
+
+// Finds the Vulkan version and the optional features supported by a physical device.
+pub unsafe fn get_device_features(
+    instance: &Instance,
+    data: &AppData,
+    physical_device: vk::PhysicalDevice,
+) -> DeviceFeatures {
+    let properties = instance.get_physical_device_properties(physical_device);
+    let api_version = data.api_version.min(properties.api_version);
+
+    let features = instance.get_physical_device_features(physical_device);
+    let mut supported = DeviceFeatures {
+        api_version,
+        sampler_anisotropy: features.sampler_anisotropy == vk::TRUE,
+        sample_rate_shading: features.sample_rate_shading == vk::TRUE,
+        fill_mode_non_solid: features.fill_mode_non_solid == vk::TRUE,
+        ..Default::default()
+    };
+
+    // Querying extended features requires Vulkan 1.1.
+    if api_version < vk::make_version(1, 1, 0) {
+        return supported;
+    }
+
+    let extensions = instance
+        .enumerate_device_extension_properties(physical_device, None)
+        .unwrap()
+        .iter()
+        .map(|e| e.extension_name)
+        .collect::<HashSet<_>>();
+
+    let dynamic_rendering_extension = DYNAMIC_RENDERING_EXTENSIONS.iter().all(|e| extensions.contains(e));
+
+    // Query
+
+    let mut vulkan_11 = vk::PhysicalDeviceVulkan11Features::builder();
+    let mut draw_parameters = vk::PhysicalDeviceShaderDrawParametersFeatures::builder();
+    let mut vulkan_12 = vk::PhysicalDeviceVulkan12Features::builder();
+    let mut vulkan_13 = vk::PhysicalDeviceVulkan13Features::builder();
+    let mut dynamic_rendering = vk::PhysicalDeviceDynamicRenderingFeatures::builder();
+
+    // Only structures for versions (and extensions) supported by the device can be chained.
+    let mut features = vk::PhysicalDeviceFeatures2::builder();
+    if api_version >= vk::make_version(1, 2, 0) {
+        features = features.push_next(&mut vulkan_11).push_next(&mut vulkan_12);
+    } else {
+        // `PhysicalDeviceVulkan11Features` was only added in Vulkan 1.2.
+        features = features.push_next(&mut draw_parameters);
+    }
+    if api_version >= vk::make_version(1, 3, 0) {
+        features = features.push_next(&mut vulkan_13);
+    } else if dynamic_rendering_extension {
+        features = features.push_next(&mut dynamic_rendering);
+    }
+
+    instance.get_physical_device_features2(physical_device, &mut features);
+
+    // Supported
+
+    supported.shader_draw_parameters =
+        vulkan_11.shader_draw_parameters == vk::TRUE || draw_parameters.shader_draw_parameters == vk::TRUE;
+    supported.timeline_semaphore = vulkan_12.timeline_semaphore == vk::TRUE;
+    supported.descriptor_indexing = vulkan_12.descriptor_indexing == vk::TRUE
+        && vulkan_12.shader_sampled_image_array_non_uniform_indexing == vk::TRUE
+        && vulkan_12.descriptor_binding_partially_bound == vk::TRUE
+        && vulkan_12.runtime_descriptor_array == vk::TRUE;
+    supported.synchronization2 = vulkan_13.synchronization2 == vk::TRUE;
+    supported.dynamic_rendering = if vulkan_13.dynamic_rendering == vk::TRUE {
+        Some(DynamicRendering::Core)
+    } else if dynamic_rendering.dynamic_rendering == vk::TRUE {
+        Some(DynamicRendering::Extension)
+    } else {
+        None
+    };
+
+    supported
+}
+
+// Formats a Vulkan version (see `vk::make_version`) as `<major>.<minor>.<patch>`.
+pub fn format_version(version: u32) -> String {
+    Version::from(version).to_string()
+}
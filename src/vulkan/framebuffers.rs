@@ -7,14 +7,25 @@ use vulkanalia::prelude::v1_0::*;
 //================================================
 
 pub unsafe fn create_framebuffers(device: &Device, data: &mut AppData) -> Result<()> {
+    // With dynamic rendering, the attachments are given when rendering begins (see `begin_rendering`).
+    if data.features.dynamic_rendering.is_some() {
+        data.framebuffers = vec![];
+        return Ok(());
+    }
+
     data.framebuffers = data
         .swapchain_image_views
         .iter()
         .map(|i| {
-            let attachments = &[data.color_image_view, data.depth_image_view, *i];
+            // Without multisampling, the swapchain image is the color attachment (see `create_render_pass`).
+            let attachments = if data.msaa_samples == vk::SampleCountFlags::_1 {
+                vec![*i, data.depth_image_view]
+            } else {
+                vec![data.color_image_view, data.depth_image_view, *i]
+            };
             let create_info = vk::FramebufferCreateInfo::builder()
                 .render_pass(data.render_pass)
-                .attachments(attachments)
+                .attachments(&attachments)
                 .width(data.swapchain_extent.width)
                 .height(data.swapchain_extent.height)
                 .layers(1);
@@ -0,0 +1,192 @@
+use super::{
+    constants::{Mat4, Vec3, Vec4},
+    model::push_submeshes,
+    structures::{AppData, DecodedImage, Material, TextureSource, Vertex},
+    texture::expand_to_rgba8,
+};
+use anyhow::{anyhow, Result};
+use cgmath::{vec2, vec3, vec4, InnerSpace, Matrix, Matrix3, SquareMatrix, Vector4, Zero};
+use gltf::{buffer, image::Format, mesh::Mode, Node};
+use log::*;
+use std::path::Path;
+
+//================================================
+// Model (glTF)
+//================================================
+
+// Loads a glTF 2.0 model (`.gltf` with external or data URI buffers, or a binary `.glb`).
+pub fn load_gltf(data: &mut AppData, path: &Path) -> Result<()> {
+    // Model
+
+    let (document, buffers, images) =
+        gltf::import(path).map_err(|e| anyhow!("Failed to load model `{}`: {}", path.display(), e))?;
+
+    // Images
+
+    data.decoded_images = images
+        .into_iter()
+        .enumerate()
+        .map(|(i, image)| {
+            let pixels = convert_to_rgba8(&image).unwrap_or_else(|| {
+                warn!(
+                    "Unsupported format for image {} in `{}`: {:?}",
+                    i,
+                    path.display(),
+                    image.format
+                );
+                vec![255; (image.width * image.height * 4) as usize]
+            });
+
+            DecodedImage {
+                pixels,
+                width: image.width,
+                height: image.height,
+            }
+        })
+        .collect();
+
+    // Materials
+
+    data.materials = document
+        .materials()
+        .map(|m| {
+            let pbr = m.pbr_metallic_roughness();
+            let [r, g, b, _] = pbr.base_color_factor();
+            Material {
+                name: m.name().unwrap_or_default().to_string(),
+                diffuse: vec3(r, g, b),
+                diffuse_texture: Some(pbr.base_color_texture().map_or(TextureSource::White, |t| {
+                    TextureSource::Decoded(t.texture().source().index())
+                })),
+                texture: 0,
+            }
+        })
+        .collect();
+
+    // Primitives without a material use glTF's default material, which is untextured and white.
+    data.materials.push(Material {
+        diffuse_texture: Some(TextureSource::White),
+        ..Default::default()
+    });
+
+    // Nodes
+
+    let scene = document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+        .ok_or_else(|| anyhow!("Model `{}` has no scenes.", path.display()))?;
+
+    let mut material_indices = vec![vec![]; data.materials.len()];
+    for node in scene.nodes() {
+        load_node(data, &buffers, &mut material_indices, &node, Mat4::identity());
+    }
+
+    // Submeshes
+
+    push_submeshes(data, material_indices);
+
+    Ok(())
+}
+
+fn load_node(
+    data: &mut AppData,
+    buffers: &[buffer::Data],
+    material_indices: &mut [Vec<u32>],
+    node: &Node,
+    parent_transform: Mat4,
+) {
+    // Node transforms are baked into the vertex positions, normals and tangents.
+    let transform = parent_transform * Mat4::from(node.transform().matrix());
+    let linear = Matrix3::from_cols(transform.x.truncate(), transform.y.truncate(), transform.z.truncate());
+    let normal_transform = linear.invert().map(|m| m.transpose()).unwrap_or(linear);
+
+    if let Some(mesh) = node.mesh() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != Mode::Triangles {
+                warn!("Skipping primitive with unsupported mode: {:?}", primitive.mode());
+                continue;
+            }
+
+            let default_material = material_indices.len() - 1;
+            let material = primitive.material().index().unwrap_or(default_material);
+            let diffuse = data.materials[material].diffuse;
+
+            let reader = primitive.reader(|b| Some(&buffers[b.index()]));
+
+            let Some(positions) = reader.read_positions() else {
+                warn!("Skipping primitive without positions.");
+                continue;
+            };
+
+            let mut tex_coords = reader.read_tex_coords(0).map(|t| t.into_f32());
+            let mut colors = reader.read_colors(0).map(|c| c.into_rgb_f32());
+            let mut normals = reader.read_normals();
+            let mut tangents = reader.read_tangents();
+
+            // Vertices
+
+            let base_vertex = data.vertices.len() as u32;
+            for position in positions {
+                let pos = transform * Vector4::new(position[0], position[1], position[2], 1.0);
+                let tex_coord = tex_coords.as_mut().and_then(|t| t.next()).unwrap_or([0.0, 0.0]);
+                let color = colors.as_mut().and_then(|c| c.next()).unwrap_or([1.0, 1.0, 1.0]);
+
+                // Missing normals and tangents are left zero and generated once the whole model is loaded.
+                let normal = normals
+                    .as_mut()
+                    .and_then(|n| n.next())
+                    .map(|n| (normal_transform * vec3(n[0], n[1], n[2])).normalize())
+                    .unwrap_or_else(Vec3::zero);
+                let tangent = tangents
+                    .as_mut()
+                    .and_then(|t| t.next())
+                    .map(|t| {
+                        let xyz = (linear * vec3(t[0], t[1], t[2])).normalize();
+                        vec4(xyz.x, xyz.y, xyz.z, t[3])
+                    })
+                    .unwrap_or_else(Vec4::zero);
+
+                data.vertices.push(Vertex {
+                    pos: pos.truncate(),
+                    color: vec3(diffuse.x * color[0], diffuse.y * color[1], diffuse.z * color[2]),
+                    tex_coord: vec2(tex_coord[0], tex_coord[1]),
+                    normal,
+                    tangent,
+                });
+            }
+
+            // Indices
+
+            let vertex_count = data.vertices.len() as u32 - base_vertex;
+            let indices = &mut material_indices[material];
+            if let Some(read_indices) = reader.read_indices() {
+                indices.extend(read_indices.into_u32().map(|i| base_vertex + i));
+            } else {
+                indices.extend(base_vertex..base_vertex + vertex_count);
+            }
+        }
+    }
+
+    for child in node.children() {
+        load_node(data, buffers, material_indices, &child, transform);
+    }
+}
+
+// Converts a decoded glTF image into tightly packed RGBA8 pixels.
+fn convert_to_rgba8(image: &gltf::image::Data) -> Option<Vec<u8>> {
+    // 16-bit channels are stored in native byte order; only their most significant byte is kept.
+    let high = if cfg!(target_endian = "little") { 1 } else { 0 };
+
+    let pixels = &image.pixels;
+    Some(match image.format {
+        Format::R8G8B8A8 => pixels.clone(),
+        Format::R8G8B8 => expand_to_rgba8(pixels, |p| [p[0], p[1], p[2], 255], 3),
+        Format::R8G8 => expand_to_rgba8(pixels, |p| [p[0], p[0], p[0], p[1]], 2),
+        Format::R8 => expand_to_rgba8(pixels, |p| [p[0], p[0], p[0], 255], 1),
+        Format::R16G16B16A16 => expand_to_rgba8(pixels, |p| [p[high], p[2 + high], p[4 + high], p[6 + high]], 8),
+        Format::R16G16B16 => expand_to_rgba8(pixels, |p| [p[high], p[2 + high], p[4 + high], 255], 6),
+        Format::R16G16 => expand_to_rgba8(pixels, |p| [p[high], p[high], p[high], p[2 + high]], 4),
+        Format::R16 => expand_to_rgba8(pixels, |p| [p[high], p[high], p[high], 255], 2),
+        _ => return None,
+    })
+}
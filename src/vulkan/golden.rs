@@ -0,0 +1,276 @@
+use super::{color_space::OutputColorSpace, config::Config, offscreen::save_png, structures::App};
+use anyhow::{anyhow, Result};
+use log::*;
+use std::{
+    fs::{self, File},
+    path::Path,
+};
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Golden Images
+//================================================
+
+// The size of the frames rendered for golden image comparisons.
+pub const GOLDEN_EXTENT: vk::Extent2D = vk::Extent2D {
+    width: 512,
+    height: 512,
+};
+
+// A fixed scene rendered and compared against a reference image.
+#[derive(Copy, Clone, Debug)]
+pub struct GoldenScene {
+    pub name: &'static str,
+    pub models: usize,
+    pub time: f32,
+}
+
+// The scenes covered by the golden image tests.
+pub const GOLDEN_SCENES: &[GoldenScene] = &[
+    GoldenScene {
+        name: "one_model",
+        models: 1,
+        time: 0.0,
+    },
+    GoldenScene {
+        name: "one_model_rotated",
+        models: 1,
+        time: 0.5,
+    },
+    GoldenScene {
+        name: "four_models",
+        models: 4,
+        time: 0.25,
+    },
+];
+
+// The result of comparing a rendered frame against a reference image.
+#[derive(Clone, Debug)]
+pub struct ImageDiff {
+    // The number of pixels with a channel that differs by more than the tolerance.
+    pub mismatched: usize,
+    // The largest difference between two channels.
+    pub max_delta: u8,
+    // An RGBA8 image highlighting the mismatched pixels in red.
+    pub pixels: Vec<u8>,
+}
+
+// The MSAA sample count of the golden scenes (4x is supported by every device).
+const GOLDEN_MSAA_SAMPLES: u32 = 4;
+
+// Renders every golden scene and compares it against its reference image in `directory`.
+//
+// When `golden_update` is set, the reference images are overwritten with the rendered frames instead.
+pub unsafe fn run_golden_tests(config: &Config, directory: &Path) -> Result<()> {
+    // Only the device and how images are compared are taken from `config`, the scenes are always rendered with the
+    // default assets and settings (without optional features like sample shading that differ between devices).
+    let config = Config {
+        headless: true,
+        width: GOLDEN_EXTENT.width,
+        height: GOLDEN_EXTENT.height,
+        golden: config.golden.clone(),
+        golden_update: config.golden_update,
+        golden_tolerance: config.golden_tolerance,
+        msaa: Some(GOLDEN_MSAA_SAMPLES),
+        sample_shading: 0.0,
+        color_space: OutputColorSpace::Sdr,
+        device: config.device.clone(),
+        ..Config::default()
+    };
+
+    let mut app = App::create_headless(&config)?;
+    let result = run_golden_scenes(&mut app, directory, config.golden_tolerance, config.golden_update);
+    app.destroy();
+    result
+}
+
+unsafe fn run_golden_scenes(app: &mut App, directory: &Path, tolerance: u8, update: bool) -> Result<()> {
+    let vk::Extent2D { width, height } = GOLDEN_EXTENT;
+
+    if update {
+        fs::create_dir_all(directory)?;
+    }
+
+    let mut failures = vec![];
+    for scene in GOLDEN_SCENES {
+        app.models = scene.models;
+        app.fixed_time = Some(scene.time);
+
+        let actual = app.render_offscreen()?;
+        let reference = directory.join(format!("{}.png", scene.name));
+
+        if update {
+            save_png(&reference, width, height, &actual)?;
+            info!("Updated golden image `{}`.", reference.display());
+            continue;
+        }
+
+        let expected = load_reference(&reference, width, height)?;
+        let diff = compare_images(&actual, &expected, tolerance);
+        if diff.mismatched == 0 {
+            info!("Golden image `{}` matches.", scene.name);
+            continue;
+        }
+
+        let actual_path = directory.join(format!("{}.actual.png", scene.name));
+        let diff_path = directory.join(format!("{}.diff.png", scene.name));
+        save_png(&actual_path, width, height, &actual)?;
+        save_png(&diff_path, width, height, &diff.pixels)?;
+
+        error!(
+            "Golden image `{}` differs in {} pixels (max delta {}), see `{}`.",
+            scene.name,
+            diff.mismatched,
+            diff.max_delta,
+            diff_path.display(),
+        );
+
+        failures.push(scene.name);
+    }
+
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("Golden images differ: {}.", failures.join(", ")))
+    }
+}
+
+// Compares two RGBA8 images of the same size channel by channel.
+//
+// Images with a different number of pixels don't match anywhere.
+pub fn compare_images(actual: &[u8], expected: &[u8], tolerance: u8) -> ImageDiff {
+    if actual.len() != expected.len() {
+        return ImageDiff {
+            mismatched: actual.len().max(expected.len()) / 4,
+            max_delta: u8::MAX,
+            pixels: [255, 0, 0, 255].repeat(actual.len() / 4),
+        };
+    }
+
+    let mut diff = ImageDiff {
+        mismatched: 0,
+        max_delta: 0,
+        pixels: Vec::with_capacity(actual.len()),
+    };
+
+    for (a, e) in actual.chunks_exact(4).zip(expected.chunks_exact(4)) {
+        let delta = a.iter().zip(e).map(|(a, e)| a.abs_diff(*e)).max().unwrap_or(0);
+        diff.max_delta = diff.max_delta.max(delta);
+
+        if delta > tolerance {
+            diff.mismatched += 1;
+            diff.pixels.extend_from_slice(&[255, 0, 0, 255]);
+        } else {
+            // Matching pixels are dimmed so the mismatches stand out.
+            let luma = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 3 / 4) as u8;
+            diff.pixels.extend_from_slice(&[luma, luma, luma, 255]);
+        }
+    }
+
+    diff
+}
+
+fn load_reference(path: &Path, width: u32, height: u32) -> Result<Vec<u8>> {
+    let file = File::open(path).map_err(|e| {
+        anyhow!(
+            "Missing golden image `{}` ({}), run with `--golden-update` to create it.",
+            path.display(),
+            e,
+        )
+    })?;
+
+    let decoder = png::Decoder::new(file);
+    let mut reader = decoder.read_info()?;
+
+    let info = reader.info();
+    if info.size() != (width, height)
+        || info.color_type != png::ColorType::Rgba
+        || info.bit_depth != png::BitDepth::Eight
+    {
+        return Err(anyhow!(
+            "Golden image `{}` is not a {}x{} RGBA8 image.",
+            path.display(),
+            width,
+            height,
+        ));
+    }
+
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels)?;
+
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, io::BufWriter, path::PathBuf};
+
+    fn temp_path(name: &str) -> PathBuf {
+        env::temp_dir().join(format!("golden-{}-{}.png", std::process::id(), name))
+    }
+
+    #[test]
+    fn compare_images_tolerance() {
+        let expected = [100, 100, 100, 255, 100, 100, 100, 255];
+        let actual = [102, 100, 100, 255, 100, 97, 100, 255];
+
+        let diff = compare_images(&actual, &expected, 2);
+        assert_eq!(diff.mismatched, 1);
+        assert_eq!(diff.max_delta, 3);
+        assert_eq!(&diff.pixels[4..], &[255, 0, 0, 255]);
+
+        let diff = compare_images(&actual, &expected, 3);
+        assert_eq!(diff.mismatched, 0);
+        assert_eq!(diff.pixels.len(), expected.len());
+    }
+
+    #[test]
+    fn compare_images_size_mismatch() {
+        let expected = [0; 16];
+        let actual = [0; 8];
+
+        let diff = compare_images(&actual, &expected, 255);
+        assert_eq!(diff.mismatched, 4);
+        assert_eq!(diff.max_delta, 255);
+    }
+
+    #[test]
+    fn load_reference_rgba8() {
+        let path = temp_path("rgba8");
+        let pixels = [1, 2, 3, 4, 5, 6, 7, 8];
+        save_png(&path, 2, 1, &pixels).unwrap();
+
+        let loaded = load_reference(&path, 2, 1);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), pixels);
+    }
+
+    #[test]
+    fn load_reference_rejects_wrong_size() {
+        let path = temp_path("size");
+        save_png(&path, 2, 1, &[0; 8]).unwrap();
+
+        let loaded = load_reference(&path, 1, 2);
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn load_reference_rejects_wrong_format() {
+        let path = temp_path("rgb8");
+        let mut encoder = png::Encoder::new(BufWriter::new(File::create(&path).unwrap()), 2, 1);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(&[0; 6]).unwrap();
+
+        let loaded = load_reference(&path, 2, 1);
+        fs::remove_file(&path).unwrap();
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn load_reference_missing() {
+        assert!(load_reference(&temp_path("missing"), 2, 1).is_err());
+    }
+}
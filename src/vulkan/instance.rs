@@ -1,5 +1,6 @@
 use super::{
     constants::{PORTABILITY_MACOS_VERSION, VALIDATION_ENABLED, VALIDATION_LAYER},
+    features::{format_version, get_instance_version},
     structures::AppData,
 };
 use anyhow::{anyhow, Result};
@@ -12,15 +13,18 @@ use winit::window::Window;
 // Instance
 //================================================
 
-pub unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData) -> Result<Instance> {
+pub unsafe fn create_instance(window: Option<&Window>, entry: &Entry, data: &mut AppData) -> Result<Instance> {
     // Application Info
 
+    data.api_version = get_instance_version(entry);
+    info!("Creating instance for Vulkan {}.", format_version(data.api_version));
+
     let application_info = vk::ApplicationInfo::builder()
         .application_name(b"Vulkan Tutorial (Rust)\0")
         .application_version(vk::make_version(1, 0, 0))
         .engine_name(b"No Engine\0")
         .engine_version(vk::make_version(1, 0, 0))
-        .api_version(vk::make_version(1, 0, 0));
+        .api_version(data.api_version);
 
     // Layers
 
@@ -43,7 +47,10 @@ pub unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData
 
     // Extensions
 
-    let mut extensions = vk_window::get_required_instance_extensions(window)
+    // Surface extensions are only needed when presenting to a window.
+    let mut extensions = window
+        .map(|w| vk_window::get_required_instance_extensions(w))
+        .unwrap_or(&[])
         .iter()
         .map(|e| e.as_ptr())
         .collect::<Vec<_>>();
@@ -62,6 +69,18 @@ pub unsafe fn create_instance(window: &Window, entry: &Entry, data: &mut AppData
         extensions.push(vk::EXT_DEBUG_UTILS_EXTENSION.name.as_ptr());
     }
 
+    // Surfaces only offer HDR and wide color gamut color spaces with this extension.
+    let available_extensions = entry
+        .enumerate_instance_extension_properties(None)
+        .unwrap()
+        .iter()
+        .map(|e| e.extension_name)
+        .collect::<HashSet<_>>();
+
+    if window.is_some() && available_extensions.contains(&vk::EXT_SWAPCHAIN_COLORSPACE_EXTENSION.name) {
+        extensions.push(vk::EXT_SWAPCHAIN_COLORSPACE_EXTENSION.name.as_ptr());
+    }
+
     // Create
 
     let mut info = vk::InstanceCreateInfo::builder()
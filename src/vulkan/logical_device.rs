@@ -1,8 +1,11 @@
 use super::{
     constants::{DEVICE_EXTENSIONS, PORTABILITY_MACOS_VERSION, VALIDATION_ENABLED, VALIDATION_LAYER},
+    dynamic_rendering::{DynamicRendering, DYNAMIC_RENDERING_EXTENSIONS},
+    features::{format_version, get_device_features},
     structures::{AppData, QueueFamilyIndices},
 };
 use anyhow::Result;
+use log::*;
 use std::collections::HashSet;
 use vulkanalia::prelude::v1_0::*;
 
@@ -14,10 +17,12 @@ pub unsafe fn create_logical_device(entry: &Entry, instance: &Instance, data: &m
     // Queue Create Infos
 
     let indices = QueueFamilyIndices::get(instance, data, data.physical_device).unwrap();
+    data.queue_families = indices;
 
     let mut unique_indices = HashSet::new();
     unique_indices.insert(indices.graphics);
     unique_indices.insert(indices.present);
+    unique_indices.insert(indices.transfer);
 
     let queue_priorities = &[1.0];
     let queue_infos = unique_indices
@@ -39,7 +44,11 @@ pub unsafe fn create_logical_device(entry: &Entry, instance: &Instance, data: &m
 
     // Extensions
 
-    let mut extensions = DEVICE_EXTENSIONS.iter().map(|n| n.as_ptr()).collect::<Vec<_>>();
+    let mut extensions = if data.headless {
+        vec![]
+    } else {
+        DEVICE_EXTENSIONS.iter().map(|n| n.as_ptr()).collect::<Vec<_>>()
+    };
 
     // Required by Vulkan SDK on macOS since 1.3.216.
     if cfg!(target_os = "macos") && entry.version().unwrap() >= PORTABILITY_MACOS_VERSION {
@@ -48,24 +57,81 @@ pub unsafe fn create_logical_device(entry: &Entry, instance: &Instance, data: &m
 
     // Features
 
+    // Every supported optional feature is enabled.
+    data.features = get_device_features(instance, data, data.physical_device);
+    let optional = data.features;
+
+    info!("Using Vulkan {}.", format_version(optional.api_version));
+    info!("Enabled optional device features: {:?}", optional.names());
+
+    match optional.dynamic_rendering {
+        Some(DynamicRendering::Extension) => {
+            extensions.extend(DYNAMIC_RENDERING_EXTENSIONS.iter().map(|n| n.as_ptr()));
+            info!("Rendering with dynamic rendering.");
+        }
+        Some(DynamicRendering::Core) => info!("Rendering with dynamic rendering."),
+        None => info!("Rendering with render passes."),
+    }
+
     let features = vk::PhysicalDeviceFeatures::builder()
-        .sampler_anisotropy(true)
-        .sample_rate_shading(true);
+        .sampler_anisotropy(optional.sampler_anisotropy)
+        .sample_rate_shading(optional.sample_rate_shading)
+        .fill_mode_non_solid(optional.fill_mode_non_solid);
+
+    let mut vulkan_11_features =
+        vk::PhysicalDeviceVulkan11Features::builder().shader_draw_parameters(optional.shader_draw_parameters);
+
+    // `PhysicalDeviceVulkan11Features` was only added in Vulkan 1.2.
+    let mut draw_parameters_features = vk::PhysicalDeviceShaderDrawParametersFeatures::builder()
+        .shader_draw_parameters(optional.shader_draw_parameters);
+
+    let mut vulkan_12_features = vk::PhysicalDeviceVulkan12Features::builder()
+        .timeline_semaphore(optional.timeline_semaphore)
+        .descriptor_indexing(optional.descriptor_indexing)
+        .shader_sampled_image_array_non_uniform_indexing(optional.descriptor_indexing)
+        .descriptor_binding_partially_bound(optional.descriptor_indexing)
+        .runtime_descriptor_array(optional.descriptor_indexing);
+
+    let mut vulkan_13_features = vk::PhysicalDeviceVulkan13Features::builder()
+        .synchronization2(optional.synchronization2)
+        .dynamic_rendering(optional.dynamic_rendering == Some(DynamicRendering::Core));
+
+    let mut dynamic_rendering_features = vk::PhysicalDeviceDynamicRenderingFeatures::builder().dynamic_rendering(true);
 
     // Create
 
-    let info = vk::DeviceCreateInfo::builder()
+    let mut info = vk::DeviceCreateInfo::builder()
         .queue_create_infos(&queue_infos)
         .enabled_layer_names(&layers)
         .enabled_extension_names(&extensions)
         .enabled_features(&features);
 
+    // Only structures for versions (and extensions) supported by the device can be chained.
+    if optional.api_version >= vk::make_version(1, 2, 0) {
+        info = info
+            .push_next(&mut vulkan_11_features)
+            .push_next(&mut vulkan_12_features);
+    } else if optional.api_version >= vk::make_version(1, 1, 0) {
+        info = info.push_next(&mut draw_parameters_features);
+    }
+    if optional.api_version >= vk::make_version(1, 3, 0) {
+        info = info.push_next(&mut vulkan_13_features);
+    } else if optional.dynamic_rendering == Some(DynamicRendering::Extension) {
+        info = info.push_next(&mut dynamic_rendering_features);
+    }
+
     let device = instance.create_device(data.physical_device, &info, None).unwrap();
 
     // Queues
 
     data.graphics_queue = device.get_device_queue(indices.graphics, 0);
     data.present_queue = device.get_device_queue(indices.present, 0);
+    data.transfer_queue = device.get_device_queue(indices.transfer, 0);
+
+    info!(
+        "Using queue families {} (graphics), {} (present) and {} (transfer).",
+        indices.graphics, indices.present, indices.transfer
+    );
 
     Ok(device)
 }
@@ -1,16 +1,29 @@
 pub mod buffers;
+pub mod camera;
 pub mod color_objects;
+pub mod color_space;
 pub mod command_buffers;
 pub mod command_pool;
+pub mod config;
 pub mod constants;
+pub mod debug_mode;
 pub mod depth_objects;
 pub mod descriptors;
+pub mod dynamic_rendering;
+pub mod features;
 pub mod framebuffers;
+pub mod gltf_model;
+pub mod golden;
 pub mod instance;
 pub mod logical_device;
 pub mod model;
+pub mod offscreen;
 pub mod physical_device;
 pub mod pipeline;
+pub mod pipeline_builder;
+pub mod pipeline_cache;
+pub mod reflection;
+pub mod shaders;
 pub mod shared_buffers;
 pub mod shared_images;
 pub mod shared_other;
@@ -18,3 +31,4 @@ pub mod structures;
 pub mod swapchain;
 pub mod sync_objects;
 pub mod texture;
+pub mod vertex;
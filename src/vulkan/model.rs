@@ -1,59 +1,499 @@
-use super::structures::{AppData, Vertex};
-use anyhow::Result;
-use cgmath::{vec2, vec3};
-use std::{collections::HashMap, fs::File, io::BufReader};
+use super::{
+    config::Normals,
+    constants::{Vec3, Vec4},
+    gltf_model::load_gltf,
+    structures::{AppData, Material, Submesh, TextureSource, Vertex},
+};
+use anyhow::{anyhow, Result};
+use cgmath::{vec2, vec3, vec4, InnerSpace, Zero};
+use log::*;
+use std::{collections::HashMap, fs::File, io::BufReader, path::Path};
 
 //================================================
 // Model
 //================================================
 
-pub fn load_model(data: &mut AppData) -> Result<()> {
+pub fn load_model(data: &mut AppData, path: &Path, normals: Normals) -> Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or_default();
+    match extension.to_ascii_lowercase().as_str() {
+        "gltf" | "glb" => load_gltf(data, path)?,
+        _ => load_obj(data, path)?,
+    }
+
+    // Normals / Tangents
+
+    if data.vertices.iter().any(|v| v.normal.is_zero()) {
+        generate_normals(data, normals);
+    }
+
+    if data.vertices.iter().any(|v| v.tangent.w == 0.0) {
+        generate_tangents(data);
+    }
+
+    info!(
+        "Loaded model `{}` ({} vertices, {} submeshes).",
+        path.display(),
+        data.vertices.len(),
+        data.submeshes.len(),
+    );
+
+    Ok(())
+}
+
+pub fn load_obj(data: &mut AppData, path: &Path) -> Result<()> {
     // Model
 
-    let mut reader = BufReader::new(File::open("src/resources/viking_room.obj").unwrap());
+    let file = File::open(path).map_err(|e| anyhow!("Failed to open model `{}`: {}", path.display(), e))?;
+    let mut reader = BufReader::new(file);
+
+    // Material libraries and their textures are relative to the model.
+    let directory = path.parent().unwrap_or_else(|| Path::new(""));
 
-    let (models, _) = tobj::load_obj_buf(
+    let (models, materials) = tobj::load_obj_buf(
         &mut reader,
         &tobj::LoadOptions {
             triangulate: true,
             ..Default::default()
         },
-        |_| Ok(Default::default()),
+        |p| tobj::load_mtl(directory.join(p)),
     )
-    .unwrap();
+    .map_err(|e| anyhow!("Failed to load model `{}`: {}", path.display(), e))?;
+
+    // Materials
+
+    let materials = materials.unwrap_or_else(|e| {
+        warn!("Failed to load materials for model `{}`: {}", path.display(), e);
+        vec![]
+    });
+
+    data.materials = materials
+        .iter()
+        .map(|m| Material {
+            name: m.name.clone(),
+            diffuse: m.diffuse.map(|d| vec3(d[0], d[1], d[2])).unwrap_or(vec3(1.0, 1.0, 1.0)),
+            diffuse_texture: Some(
+                m.diffuse_texture
+                    .as_ref()
+                    .map_or(TextureSource::White, |t| TextureSource::File(directory.join(t))),
+            ),
+            texture: 0,
+        })
+        .collect();
+
+    // Meshes without a (known) material use a default material with the configured texture.
+    let default_material = data.materials.len();
+    data.materials.push(Material::default());
 
     // Vertices / Indices
 
     let mut unique_vertices = HashMap::new();
+    let mut material_indices = vec![vec![]; data.materials.len()];
 
     for model in &models {
-        for index in &model.mesh.indices {
+        let material = model
+            .mesh
+            .material_id
+            .filter(|m| *m < default_material)
+            .unwrap_or(default_material);
+
+        let mesh = &model.mesh;
+        for (i, index) in mesh.indices.iter().enumerate() {
+            // Texture coordinates and normals may be indexed separately from positions.
+            let tex_coord_index = mesh.texcoord_indices.get(i).unwrap_or(index);
+            let normal_index = mesh.normal_indices.get(i).unwrap_or(index);
+
             let pos_offset = (3 * index) as usize;
-            let tex_coord_offset = (2 * index) as usize;
+            let tex_coord_offset = (2 * tex_coord_index) as usize;
+            let normal_offset = (3 * normal_index) as usize;
+
+            let tex_coord = if mesh.texcoords.is_empty() {
+                vec2(0.0, 0.0)
+            } else {
+                vec2(
+                    mesh.texcoords[tex_coord_offset],
+                    1.0 - mesh.texcoords[tex_coord_offset + 1],
+                )
+            };
+
+            let normal = if mesh.normals.is_empty() {
+                Vec3::zero()
+            } else {
+                vec3(
+                    mesh.normals[normal_offset],
+                    mesh.normals[normal_offset + 1],
+                    mesh.normals[normal_offset + 2],
+                )
+            };
 
             let vertex = Vertex {
                 pos: vec3(
-                    model.mesh.positions[pos_offset],
-                    model.mesh.positions[pos_offset + 1],
-                    model.mesh.positions[pos_offset + 2],
-                ),
-                color: vec3(1.0, 1.0, 1.0),
-                tex_coord: vec2(
-                    model.mesh.texcoords[tex_coord_offset],
-                    1.0 - model.mesh.texcoords[tex_coord_offset + 1],
+                    mesh.positions[pos_offset],
+                    mesh.positions[pos_offset + 1],
+                    mesh.positions[pos_offset + 2],
                 ),
+                color: data.materials[material].diffuse,
+                tex_coord,
+                normal,
+                tangent: Vec4::zero(),
             };
 
             if let Some(index) = unique_vertices.get(&vertex) {
-                data.indices.push(*index as u32);
+                material_indices[material].push(*index as u32);
             } else {
                 let index = data.vertices.len();
                 unique_vertices.insert(vertex, index);
                 data.vertices.push(vertex);
-                data.indices.push(index as u32);
+                material_indices[material].push(index as u32);
             }
         }
     }
 
+    // Submeshes
+
+    push_submeshes(data, material_indices);
+
     Ok(())
 }
+
+// Appends the indices of each material to the index buffer as one submesh per material.
+pub fn push_submeshes(data: &mut AppData, material_indices: Vec<Vec<u32>>) {
+    for (material, indices) in material_indices.into_iter().enumerate() {
+        if indices.is_empty() {
+            continue;
+        }
+
+        data.submeshes.push(Submesh {
+            first_index: data.indices.len() as u32,
+            index_count: indices.len() as u32,
+            material,
+        });
+
+        data.indices.extend(indices);
+    }
+}
+
+// Generates the normals missing from the model.
+pub fn generate_normals(data: &mut AppData, normals: Normals) {
+    match normals {
+        Normals::Smooth => generate_smooth_normals(data),
+        Normals::Flat => generate_flat_normals(data),
+    }
+}
+
+// Averages the area-weighted normals of the faces around each position.
+fn generate_smooth_normals(data: &mut AppData) {
+    let key = |v: &Vertex| [v.pos.x.to_bits(), v.pos.y.to_bits(), v.pos.z.to_bits()];
+
+    let mut position_normals = HashMap::new();
+    for triangle in data.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| &data.vertices[triangle[i] as usize]);
+        let normal = (b.pos - a.pos).cross(c.pos - a.pos);
+        for vertex in [a, b, c] {
+            *position_normals.entry(key(vertex)).or_insert_with(Vec3::zero) += normal;
+        }
+    }
+
+    for vertex in data.vertices.iter_mut().filter(|v| v.normal.is_zero()) {
+        let normal = position_normals.get(&key(vertex)).copied().unwrap_or_else(Vec3::zero);
+        vertex.normal = normalize_or(normal, vec3(0.0, 0.0, 1.0));
+    }
+}
+
+// Gives the corners of the faces without normals the face normal.
+//
+// Corners can't be shared between faces with different normals, so every face after the first one that uses a corner
+// without a normal gets its own copy of that corner.
+fn generate_flat_normals(data: &mut AppData) {
+    let missing = data.vertices.iter().map(|v| v.normal.is_zero()).collect::<Vec<_>>();
+    let mut claimed = vec![false; data.vertices.len()];
+
+    for triangle in data.indices.chunks_exact_mut(3) {
+        if !triangle.iter().any(|i| missing[*i as usize]) {
+            continue;
+        }
+
+        let [a, b, c] = [0, 1, 2].map(|i| data.vertices[triangle[i] as usize].pos);
+        let normal = normalize_or((b - a).cross(c - a), vec3(0.0, 0.0, 1.0));
+
+        for index in triangle.iter_mut().filter(|i| missing[**i as usize]) {
+            if claimed[*index as usize] {
+                let vertex = Vertex {
+                    normal,
+                    ..data.vertices[*index as usize]
+                };
+                *index = data.vertices.len() as u32;
+                data.vertices.push(vertex);
+            } else {
+                claimed[*index as usize] = true;
+                data.vertices[*index as usize].normal = normal;
+            }
+        }
+    }
+}
+
+// Generates the tangents missing from the model from its texture coordinates.
+//
+// Like MikkTSpace, the tangents of the faces around a vertex are accumulated, orthogonalized against the vertex normal
+// and given a handedness (`w`) from the bitangent, so that `bitangent = cross(normal, tangent.xyz) * tangent.w`.
+pub fn generate_tangents(data: &mut AppData) {
+    let mut tangents = vec![Vec3::zero(); data.vertices.len()];
+    let mut bitangents = vec![Vec3::zero(); data.vertices.len()];
+
+    for triangle in data.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| &data.vertices[triangle[i] as usize]);
+
+        let edge1 = b.pos - a.pos;
+        let edge2 = c.pos - a.pos;
+        let delta1 = b.tex_coord - a.tex_coord;
+        let delta2 = c.tex_coord - a.tex_coord;
+
+        let determinant = delta1.x * delta2.y - delta2.x * delta1.y;
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+
+        let r = 1.0 / determinant;
+        let tangent = (edge1 * delta2.y - edge2 * delta1.y) * r;
+        let bitangent = (edge2 * delta1.x - edge1 * delta2.x) * r;
+
+        for index in triangle {
+            tangents[*index as usize] += tangent;
+            bitangents[*index as usize] += bitangent;
+        }
+    }
+
+    for (i, vertex) in data.vertices.iter_mut().enumerate() {
+        if vertex.tangent.w != 0.0 {
+            continue;
+        }
+
+        // Gram-Schmidt orthogonalization, falling back to any perpendicular vector without usable texture coordinates.
+        let n = vertex.normal;
+        let fallback = if n.x.abs() < 0.9 {
+            vec3(1.0, 0.0, 0.0)
+        } else {
+            vec3(0.0, 1.0, 0.0)
+        };
+        let t = normalize_or(
+            tangents[i] - n * n.dot(tangents[i]),
+            normalize_or(fallback - n * n.dot(fallback), fallback),
+        );
+        let w = if n.cross(t).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+
+        vertex.tangent = vec4(t.x, t.y, t.z, w);
+    }
+}
+
+fn normalize_or(vector: Vec3, fallback: Vec3) -> Vec3 {
+    if vector.magnitude2() > f32::EPSILON * f32::EPSILON {
+        vector.normalize()
+    } else {
+        fallback
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(pos: [f32; 3], tex_coord: [f32; 2], normal: [f32; 3]) -> Vertex {
+        Vertex::new(
+            pos.into(),
+            vec3(1.0, 1.0, 1.0),
+            tex_coord.into(),
+            normal.into(),
+            Vec4::zero(),
+        )
+    }
+
+    fn model(vertices: Vec<Vertex>, indices: Vec<u32>) -> AppData {
+        AppData {
+            vertices,
+            indices,
+            ..Default::default()
+        }
+    }
+
+    fn assert_close(actual: Vec3, expected: Vec3) {
+        assert!((actual - expected).magnitude() < 1e-5, "{:?} != {:?}", actual, expected);
+    }
+
+    #[test]
+    fn smooth_normals() {
+        // Two faces (facing +Z and +Y) that share an edge but not its vertices.
+        let mut data = model(
+            vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 0.0, 1.0], [0.0, 0.0], [1.0, 0.0, 0.0]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+            ],
+            vec![0, 1, 2, 3, 4, 5],
+        );
+
+        generate_normals(&mut data, Normals::Smooth);
+
+        let shared = vec3(0.0, 1.0, 1.0).normalize();
+        assert_close(data.vertices[0].normal, shared);
+        assert_close(data.vertices[1].normal, shared);
+        assert_close(data.vertices[2].normal, vec3(0.0, 0.0, 1.0));
+        assert_close(data.vertices[3].normal, shared);
+        assert_close(data.vertices[5].normal, shared);
+
+        // Supplied normals are kept.
+        assert_close(data.vertices[4].normal, vec3(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn smooth_normals_degenerate() {
+        let mut data = model(
+            vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([2.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+            ],
+            vec![0, 1, 2],
+        );
+
+        generate_normals(&mut data, Normals::Smooth);
+
+        for vertex in &data.vertices {
+            assert_close(vertex.normal, vec3(0.0, 0.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn flat_normals_split_shared_corners() {
+        // Two faces (facing +Z and -X) that share the corners 0 and 2.
+        let mut data = model(
+            vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 0.0, -1.0], [0.0, 0.0], [0.0; 3]),
+            ],
+            vec![0, 1, 2, 0, 2, 3],
+        );
+
+        generate_normals(&mut data, Normals::Flat);
+
+        // The first face keeps the shared corners, the second one gets copies of them.
+        assert_eq!(data.indices, [0, 1, 2, 4, 5, 3]);
+        assert_eq!(data.vertices.len(), 6);
+
+        for index in [0, 1, 2] {
+            assert_close(data.vertices[index].normal, vec3(0.0, 0.0, 1.0));
+        }
+        for index in [4, 5, 3] {
+            assert_close(data.vertices[index].normal, vec3(-1.0, 0.0, 0.0));
+        }
+
+        assert_eq!(data.vertices[4].pos, data.vertices[0].pos);
+        assert_eq!(data.vertices[5].pos, data.vertices[2].pos);
+    }
+
+    #[test]
+    fn flat_normals_keep_supplied_normals() {
+        let mut data = model(
+            vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([1.0, 0.0, 0.0], [0.0, 0.0], [1.0, 0.0, 0.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([1.0, 1.0, 0.0], [0.0, 0.0], [0.0, 1.0, 0.0]),
+            ],
+            vec![0, 1, 2, 1, 3, 2],
+        );
+
+        generate_normals(&mut data, Normals::Flat);
+
+        // Only the corners without normals of the first face are changed; the second face only shares its corner with
+        // a generated normal, which it gets a copy of.
+        assert_close(data.vertices[0].normal, vec3(0.0, 0.0, 1.0));
+        assert_close(data.vertices[1].normal, vec3(1.0, 0.0, 0.0));
+        assert_close(data.vertices[2].normal, vec3(0.0, 0.0, 1.0));
+        assert_close(data.vertices[3].normal, vec3(0.0, 1.0, 0.0));
+        assert_eq!(data.indices, [0, 1, 2, 1, 3, 4]);
+
+        // Faces whose corners all have normals are left alone.
+        let mut data = model(data.vertices[..4].to_vec(), vec![0, 1, 2]);
+        generate_normals(&mut data, Normals::Flat);
+        assert_eq!(data.vertices.len(), 4);
+    }
+
+    #[test]
+    fn flat_normals_degenerate() {
+        let mut data = model(
+            vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0; 3]),
+            ],
+            vec![0, 1, 2],
+        );
+
+        generate_normals(&mut data, Normals::Flat);
+
+        for vertex in &data.vertices {
+            assert_close(vertex.normal, vec3(0.0, 0.0, 1.0));
+        }
+    }
+
+    fn triangle(u: f32) -> AppData {
+        model(
+            vec![
+                vertex([0.0, 0.0, 0.0], [0.0, 0.0], [0.0, 0.0, 1.0]),
+                vertex([1.0, 0.0, 0.0], [u, 0.0], [0.0, 0.0, 1.0]),
+                vertex([0.0, 1.0, 0.0], [0.0, 1.0], [0.0, 0.0, 1.0]),
+            ],
+            vec![0, 1, 2],
+        )
+    }
+
+    #[test]
+    fn tangents() {
+        let mut data = triangle(1.0);
+        generate_tangents(&mut data);
+        for vertex in &data.vertices {
+            assert_close(vertex.tangent.truncate(), vec3(1.0, 0.0, 0.0));
+            assert_eq!(vertex.tangent.w, 1.0);
+        }
+
+        // Mirrored texture coordinates flip the tangent and its handedness.
+        let mut data = triangle(-1.0);
+        generate_tangents(&mut data);
+        for vertex in &data.vertices {
+            assert_close(vertex.tangent.truncate(), vec3(-1.0, 0.0, 0.0));
+            assert_eq!(vertex.tangent.w, -1.0);
+        }
+    }
+
+    #[test]
+    fn tangents_zero_area_tex_coords() {
+        // Without usable texture coordinates, the tangent is any vector perpendicular to the normal.
+        let mut data = triangle(0.0);
+        data.vertices[2].tex_coord = vec2(0.0, 0.0);
+        generate_tangents(&mut data);
+        for vertex in &data.vertices {
+            assert_close(vertex.tangent.truncate(), vec3(1.0, 0.0, 0.0));
+            assert_eq!(vertex.tangent.w, 1.0);
+        }
+
+        data.vertices.iter_mut().for_each(|v| v.normal = vec3(1.0, 0.0, 0.0));
+        data.vertices.iter_mut().for_each(|v| v.tangent = Vec4::zero());
+        generate_tangents(&mut data);
+        for vertex in &data.vertices {
+            assert_close(vertex.tangent.truncate(), vec3(0.0, 1.0, 0.0));
+        }
+    }
+
+    #[test]
+    fn tangents_keep_supplied_tangents() {
+        let mut data = triangle(1.0);
+        data.vertices[0].tangent = vec4(0.0, 1.0, 0.0, -1.0);
+        generate_tangents(&mut data);
+        assert_eq!(data.vertices[0].tangent, vec4(0.0, 1.0, 0.0, -1.0));
+        assert_close(data.vertices[1].tangent.truncate(), vec3(1.0, 0.0, 0.0));
+    }
+}
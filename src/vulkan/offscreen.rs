@@ -0,0 +1,158 @@
+use super::{
+    shared_buffers::create_buffer,
+    shared_images::create_image,
+    shared_other::{begin_single_time_commands, end_single_time_commands},
+    structures::AppData,
+};
+use anyhow::Result;
+use std::{fs::File, io::BufWriter, path::Path, ptr::copy_nonoverlapping as memcpy};
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Offscreen
+//================================================
+
+// The format of the color image rendered to when running without a surface.
+pub const OFFSCREEN_FORMAT: vk::Format = vk::Format::R8G8B8A8_SRGB;
+
+// Creates the color image that stands in for the swapchain images when running without a surface.
+pub unsafe fn create_offscreen_objects(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    extent: vk::Extent2D,
+) -> Result<()> {
+    // Image + Image Memory
+
+    let (offscreen_image, offscreen_image_memory) = create_image(
+        instance,
+        device,
+        data,
+        extent.width,
+        extent.height,
+        1,
+        vk::SampleCountFlags::_1,
+        OFFSCREEN_FORMAT,
+        vk::ImageTiling::OPTIMAL,
+        vk::ImageUsageFlags::COLOR_ATTACHMENT | vk::ImageUsageFlags::TRANSFER_SRC,
+        vk::MemoryPropertyFlags::DEVICE_LOCAL,
+    )
+    .unwrap();
+
+    data.swapchain_format = OFFSCREEN_FORMAT;
+    data.swapchain_extent = extent;
+    data.swapchain_images = vec![offscreen_image];
+    data.offscreen_image_memory = offscreen_image_memory;
+
+    Ok(())
+}
+
+// Copies the resolved offscreen color image into host memory as tightly packed RGBA8 pixels.
+pub unsafe fn read_offscreen_image(instance: &Instance, device: &Device, data: &AppData) -> Result<Vec<u8>> {
+    // Create (staging)
+
+    let width = data.swapchain_extent.width;
+    let height = data.swapchain_extent.height;
+    let size = (width * height * 4) as u64;
+
+    let (staging_buffer, staging_buffer_memory) = create_buffer(
+        instance,
+        device,
+        data,
+        size,
+        vk::BufferUsageFlags::TRANSFER_DST,
+        vk::MemoryPropertyFlags::HOST_COHERENT | vk::MemoryPropertyFlags::HOST_VISIBLE,
+    )
+    .unwrap();
+
+    // Copy (image)
+
+    let command_buffer = begin_single_time_commands(device, data).unwrap();
+
+    let subresource_range = vk::ImageSubresourceRange::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    let barrier = vk::ImageMemoryBarrier::builder()
+        .old_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+        .new_layout(vk::ImageLayout::TRANSFER_SRC_OPTIMAL)
+        .src_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .dst_queue_family_index(vk::QUEUE_FAMILY_IGNORED)
+        .image(data.swapchain_images[0])
+        .subresource_range(subresource_range)
+        .src_access_mask(vk::AccessFlags::COLOR_ATTACHMENT_WRITE)
+        .dst_access_mask(vk::AccessFlags::TRANSFER_READ);
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &[] as &[vk::BufferMemoryBarrier],
+        &[barrier],
+    );
+
+    let subresource = vk::ImageSubresourceLayers::builder()
+        .aspect_mask(vk::ImageAspectFlags::COLOR)
+        .mip_level(0)
+        .base_array_layer(0)
+        .layer_count(1);
+
+    let region = vk::BufferImageCopy::builder()
+        .buffer_offset(0)
+        .buffer_row_length(0)
+        .buffer_image_height(0)
+        .image_subresource(subresource)
+        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+        .image_extent(vk::Extent3D {
+            width,
+            height,
+            depth: 1,
+        });
+
+    device.cmd_copy_image_to_buffer(
+        command_buffer,
+        data.swapchain_images[0],
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL,
+        staging_buffer,
+        &[region],
+    );
+
+    end_single_time_commands(device, data, command_buffer).unwrap();
+
+    // Copy (staging)
+
+    let memory = device
+        .map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())
+        .unwrap();
+
+    let mut pixels = vec![0; size as usize];
+    memcpy(memory.cast(), pixels.as_mut_ptr(), pixels.len());
+
+    device.unmap_memory(staging_buffer_memory);
+
+    // Cleanup
+
+    device.destroy_buffer(staging_buffer, None);
+    device.free_memory(staging_buffer_memory, None);
+
+    Ok(pixels)
+}
+
+// Writes tightly packed RGBA8 pixels to a PNG file.
+pub fn save_png(path: &Path, width: u32, height: u32, pixels: &[u8]) -> Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(pixels)?;
+
+    Ok(())
+}
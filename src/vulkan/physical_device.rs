@@ -1,10 +1,11 @@
 use super::{
-    constants::DEVICE_EXTENSIONS,
+    constants::{DEVICE_EXTENSIONS, MAX_MSAA_SAMPLES},
+    features::get_device_features,
     structures::{AppData, QueueFamilyIndices, SwapchainSupport},
 };
 use anyhow::{anyhow, Result};
 use log::*;
-use std::collections::HashSet;
+use std::{cmp::Reverse, collections::HashSet};
 use thiserror::Error;
 use vulkanalia::prelude::v1_0::*;
 
@@ -16,21 +17,125 @@ use vulkanalia::prelude::v1_0::*;
 #[error("{0}")]
 pub struct SuitabilityError(pub &'static str);
 
-pub unsafe fn pick_physical_device(instance: &Instance, data: &mut AppData) -> Result<()> {
-    for physical_device in instance.enumerate_physical_devices().unwrap() {
+// A physical device considered by `pick_physical_device`.
+struct Candidate {
+    index: usize,
+    name: String,
+    physical_device: vk::PhysicalDevice,
+    // The score of a suitable device, or why it is unsuitable.
+    score: Result<u32>,
+}
+
+// Picks the suitable physical device with the highest score, or the one selected by index or (part of its) name.
+pub unsafe fn pick_physical_device(instance: &Instance, data: &mut AppData, selection: Option<&str>) -> Result<()> {
+    // Candidates
+
+    let mut candidates = vec![];
+    for (index, physical_device) in instance.enumerate_physical_devices().unwrap().into_iter().enumerate() {
         let properties = instance.get_physical_device_properties(physical_device);
+        let name = properties.device_name.to_string();
+
+        let score = check_physical_device(instance, data, physical_device)
+            .map(|_| score_physical_device(instance, data, physical_device));
+        match &score {
+            Ok(score) => info!(
+                "Physical device {} (`{}`, {:?}): score {}.",
+                index, name, properties.device_type, score
+            ),
+            Err(error) => warn!(
+                "Physical device {} (`{}`, {:?}): rejected: {}",
+                index, name, properties.device_type, error
+            ),
+        }
 
-        if let Err(error) = check_physical_device(instance, data, physical_device) {
-            warn!("Skipping physical device (`{}`): {}", properties.device_name, error);
+        candidates.push(Candidate {
+            index,
+            name,
+            physical_device,
+            score,
+        });
+    }
+
+    // Selection
+
+    let candidate = if let Some(selection) = selection {
+        // A number is always an index, so it can't pick a device whose name contains it instead.
+        let candidate = if let Ok(index) = selection.parse::<usize>() {
+            candidates.iter().find(|c| c.index == index)
         } else {
-            info!("Selected physical device (`{}`).", properties.device_name);
-            data.physical_device = physical_device;
-            data.msaa_samples = get_max_msaa_samples(instance, data);
-            return Ok(());
+            let pattern = selection.to_lowercase();
+            candidates.iter().find(|c| c.name.to_lowercase().contains(&pattern))
+        };
+
+        let candidate = candidate.ok_or_else(|| anyhow!("No physical device matches `{}`.", selection))?;
+
+        if let Err(error) = &candidate.score {
+            return Err(anyhow!(
+                "Physical device `{}` is not suitable: {}",
+                candidate.name,
+                error
+            ));
         }
+
+        candidate
+    } else {
+        // Ties go to the device enumerated first.
+        candidates
+            .iter()
+            .filter_map(|c| c.score.as_ref().ok().map(|s| (c, *s)))
+            .max_by_key(|(c, s)| (*s, Reverse(c.index)))
+            .map(|(c, _)| c)
+            .ok_or_else(|| anyhow!("Failed to find suitable physical device."))?
+    };
+
+    info!("Selected physical device {} (`{}`).", candidate.index, candidate.name);
+    data.physical_device = candidate.physical_device;
+    data.msaa_sample_counts = get_msaa_sample_counts(instance, data);
+    data.msaa_samples = *data.msaa_sample_counts.last().unwrap();
+    data.texture_linear_filtering = supports_texture_linear_filtering(instance, data);
+
+    // Linear filtering is optional for our texture format.
+    if !data.texture_linear_filtering {
+        warn!(
+            "Texture format does not support linear filtering, \
+            generating mipmaps on the CPU and sampling with nearest filtering."
+        );
     }
 
-    Err(anyhow!("Failed to find suitable physical device."))
+    Ok(())
+}
+
+// Whether our texture format can be sampled (and blitted) with linear filtering.
+unsafe fn supports_texture_linear_filtering(instance: &Instance, data: &AppData) -> bool {
+    instance
+        .get_physical_device_format_properties(data.physical_device, vk::Format::R8G8B8A8_SRGB)
+        .optimal_tiling_features
+        .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
+}
+
+// Scores a suitable physical device by its type, then its memory and then its optional features.
+unsafe fn score_physical_device(instance: &Instance, data: &AppData, physical_device: vk::PhysicalDevice) -> u32 {
+    let properties = instance.get_physical_device_properties(physical_device);
+    let type_score = match properties.device_type {
+        vk::PhysicalDeviceType::DISCRETE_GPU => 40_000,
+        vk::PhysicalDeviceType::INTEGRATED_GPU => 30_000,
+        vk::PhysicalDeviceType::VIRTUAL_GPU => 20_000,
+        vk::PhysicalDeviceType::CPU => 10_000,
+        _ => 0,
+    };
+
+    // 100 per GiB of device local memory, capped so memory never outweighs the type.
+    let memory = instance.get_physical_device_memory_properties(physical_device);
+    let bytes = memory.memory_heaps[..memory.memory_heap_count as usize]
+        .iter()
+        .filter(|h| h.flags.contains(vk::MemoryHeapFlags::DEVICE_LOCAL))
+        .map(|h| h.size)
+        .sum::<u64>();
+    let memory_score = ((bytes >> 30) * 100).min(9_000) as u32;
+
+    let features_score = get_device_features(instance, data, physical_device).names().len() as u32 * 10;
+
+    type_score + memory_score + features_score
 }
 
 unsafe fn check_physical_device(
@@ -38,17 +143,16 @@ unsafe fn check_physical_device(
     data: &AppData,
     physical_device: vk::PhysicalDevice,
 ) -> Result<()> {
-    QueueFamilyIndices::get(instance, data, physical_device).unwrap();
-    check_physical_device_extensions(instance, physical_device).unwrap();
+    QueueFamilyIndices::get(instance, data, physical_device)?;
 
-    let support = SwapchainSupport::get(instance, data, physical_device).unwrap();
-    if support.formats.is_empty() || support.present_modes.is_empty() {
-        return Err(anyhow!(SuitabilityError("Insufficient swapchain support.")));
-    }
+    // Presentation is only required when rendering to a window.
+    if !data.headless {
+        check_physical_device_extensions(instance, physical_device)?;
 
-    let features = instance.get_physical_device_features(physical_device);
-    if features.sampler_anisotropy != vk::TRUE {
-        return Err(anyhow!(SuitabilityError("No sampler anisotropy.")));
+        let support = SwapchainSupport::get(instance, data, physical_device).unwrap();
+        if support.formats.is_empty() || support.present_modes.is_empty() {
+            return Err(anyhow!(SuitabilityError("Insufficient swapchain support.")));
+        }
     }
 
     Ok(())
@@ -68,19 +172,43 @@ unsafe fn check_physical_device_extensions(instance: &Instance, physical_device:
     }
 }
 
-unsafe fn get_max_msaa_samples(instance: &Instance, data: &AppData) -> vk::SampleCountFlags {
+// Finds the MSAA sample counts supported for both color and depth attachments, up to `MAX_MSAA_SAMPLES`.
+unsafe fn get_msaa_sample_counts(instance: &Instance, data: &AppData) -> Vec<vk::SampleCountFlags> {
     let properties = instance.get_physical_device_properties(data.physical_device);
     let counts = properties.limits.framebuffer_color_sample_counts & properties.limits.framebuffer_depth_sample_counts;
     [
-        vk::SampleCountFlags::_64,
-        vk::SampleCountFlags::_32,
-        vk::SampleCountFlags::_16,
-        vk::SampleCountFlags::_8,
-        vk::SampleCountFlags::_4,
+        vk::SampleCountFlags::_1,
         vk::SampleCountFlags::_2,
+        vk::SampleCountFlags::_4,
+        vk::SampleCountFlags::_8,
+        vk::SampleCountFlags::_16,
+        vk::SampleCountFlags::_32,
+        vk::SampleCountFlags::_64,
     ]
     .iter()
     .cloned()
-    .find(|c| counts.contains(*c))
-    .unwrap_or(vk::SampleCountFlags::_1)
+    .filter(|c| c.bits() <= MAX_MSAA_SAMPLES.bits())
+    .filter(|c| *c == vk::SampleCountFlags::_1 || counts.contains(*c))
+    .collect()
+}
+
+// Finds the highest supported MSAA sample count that doesn't exceed the requested sample count.
+pub fn find_msaa_samples(data: &AppData, requested: u32) -> vk::SampleCountFlags {
+    let samples = data
+        .msaa_sample_counts
+        .iter()
+        .rev()
+        .find(|c| c.bits() <= requested)
+        .cloned()
+        .unwrap_or(vk::SampleCountFlags::_1);
+
+    if samples.bits() != requested {
+        warn!(
+            "{}x MSAA is not supported, using {}x instead.",
+            requested,
+            samples.bits()
+        );
+    }
+
+    samples
 }
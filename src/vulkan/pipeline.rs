@@ -1,8 +1,13 @@
 use super::{
+    color_space::{OUTPUT_CONSTANT_ID, PAPER_WHITE_CONSTANT_ID},
+    constants::Mat4,
+    debug_mode::DebugMode,
     depth_objects::get_depth_format,
-    structures::{AppData, Vertex},
+    reflection::reflect_pipeline,
+    structures::{AppData, UniformBufferObject},
 };
 use anyhow::Result;
+use std::mem::{offset_of, size_of};
 use vulkanalia::{bytecode::Bytecode, prelude::v1_0::*};
 
 //================================================
@@ -10,8 +15,25 @@ use vulkanalia::{bytecode::Bytecode, prelude::v1_0::*};
 //================================================
 
 pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mut AppData) -> Result<()> {
+    data.depth_format = get_depth_format(instance, data)?;
+
+    // With dynamic rendering, the attachments are given when rendering begins (see `begin_rendering`).
+    if data.features.dynamic_rendering.is_some() {
+        return Ok(());
+    }
+
     // Attachments
 
+    // Offscreen frames are copied out of the swapchain image instead of presented.
+    let final_layout = if data.headless {
+        vk::ImageLayout::TRANSFER_SRC_OPTIMAL
+    } else {
+        vk::ImageLayout::PRESENT_SRC_KHR
+    };
+
+    // Without multisampling, the color attachment is the swapchain image itself and there is nothing to resolve.
+    let resolve = data.msaa_samples != vk::SampleCountFlags::_1;
+
     let color_attachment = vk::AttachmentDescription::builder()
         .format(data.swapchain_format)
         .samples(data.msaa_samples)
@@ -20,10 +42,14 @@ pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mu
         .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
         .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
         .initial_layout(vk::ImageLayout::UNDEFINED)
-        .final_layout(vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL);
+        .final_layout(if resolve {
+            vk::ImageLayout::COLOR_ATTACHMENT_OPTIMAL
+        } else {
+            final_layout
+        });
 
     let depth_stencil_attachment = vk::AttachmentDescription::builder()
-        .format(get_depth_format(instance, data).unwrap())
+        .format(data.depth_format)
         .samples(data.msaa_samples)
         .load_op(vk::AttachmentLoadOp::CLEAR)
         .store_op(vk::AttachmentStoreOp::DONT_CARE)
@@ -40,7 +66,7 @@ pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mu
         .stencil_load_op(vk::AttachmentLoadOp::DONT_CARE)
         .stencil_store_op(vk::AttachmentStoreOp::DONT_CARE)
         .initial_layout(vk::ImageLayout::UNDEFINED)
-        .final_layout(vk::ImageLayout::PRESENT_SRC_KHR);
+        .final_layout(final_layout);
 
     // Subpasses
 
@@ -58,11 +84,14 @@ pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mu
 
     let color_attachments = &[color_attachment_ref];
     let resolve_attachments = &[color_resolve_attachment_ref];
-    let subpass = vk::SubpassDescription::builder()
+    let mut subpass = vk::SubpassDescription::builder()
         .pipeline_bind_point(vk::PipelineBindPoint::GRAPHICS)
         .color_attachments(color_attachments)
-        .depth_stencil_attachment(&depth_stencil_attachment_ref)
-        .resolve_attachments(resolve_attachments);
+        .depth_stencil_attachment(&depth_stencil_attachment_ref);
+
+    if resolve {
+        subpass = subpass.resolve_attachments(resolve_attachments);
+    }
 
     // Dependencies
 
@@ -76,11 +105,15 @@ pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mu
 
     // Create
 
-    let attachments = &[color_attachment, depth_stencil_attachment, color_resolve_attachment];
+    let attachments = if resolve {
+        vec![color_attachment, depth_stencil_attachment, color_resolve_attachment]
+    } else {
+        vec![color_attachment, depth_stencil_attachment]
+    };
     let subpasses = &[subpass];
     let dependencies = &[dependency];
     let info = vk::RenderPassCreateInfo::builder()
-        .attachments(attachments)
+        .attachments(&attachments)
         .subpasses(subpasses)
         .dependencies(dependencies);
 
@@ -89,183 +122,66 @@ pub unsafe fn create_render_pass(instance: &Instance, device: &Device, data: &mu
     Ok(())
 }
 
+// Creates the descriptor set layout shared by every pipeline, declaring the descriptors used by any loaded shader.
 pub unsafe fn create_descriptor_set_layout(device: &Device, data: &mut AppData) -> Result<()> {
-    let ubo_binding = vk::DescriptorSetLayoutBinding::builder()
-        .binding(0)
-        .descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)
-        .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::VERTEX);
-
-    let sampler_binding = vk::DescriptorSetLayoutBinding::builder()
-        .binding(1)
-        .descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)
-        .descriptor_count(1)
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT);
-
-    let bindings = &[ubo_binding, sampler_binding];
-    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(bindings);
+    let shaders = data.shaders.values().map(|c| &c[..]).collect::<Vec<_>>();
+    let reflection = reflect_pipeline(&shaders, &[])?;
+
+    // The descriptors written by `create_descriptor_sets`.
+    reflection.check_binding(
+        0,
+        vk::DescriptorType::UNIFORM_BUFFER,
+        Some(size_of::<UniformBufferObject>()),
+    )?;
+    reflection.check_members(
+        0,
+        &[
+            ("view", offset_of!(UniformBufferObject, view), size_of::<Mat4>()),
+            ("proj", offset_of!(UniformBufferObject, proj), size_of::<Mat4>()),
+        ],
+    )?;
+    reflection.check_binding(1, vk::DescriptorType::COMBINED_IMAGE_SAMPLER, None)?;
+
+    let bindings = reflection.descriptor_set_layout_bindings()?;
+    let info = vk::DescriptorSetLayoutCreateInfo::builder().bindings(&bindings);
 
     data.descriptor_set_layout = device.create_descriptor_set_layout(&info, None).unwrap();
+    data.descriptor_bindings = reflection.bindings;
 
     Ok(())
 }
 
-pub unsafe fn create_pipeline(device: &Device, data: &mut AppData) -> Result<()> {
-    // Stages
-
-    let vert = include_bytes!("../shaders/vert.spv");
-    let frag = include_bytes!("../shaders/frag.spv");
-
-    let vert_shader_module = create_shader_module(device, &vert[..]).unwrap();
-    let frag_shader_module = create_shader_module(device, &frag[..]).unwrap();
-
-    let vert_stage = vk::PipelineShaderStageCreateInfo::builder()
-        .stage(vk::ShaderStageFlags::VERTEX)
-        .module(vert_shader_module)
-        .name(b"main\0");
-
-    let frag_stage = vk::PipelineShaderStageCreateInfo::builder()
-        .stage(vk::ShaderStageFlags::FRAGMENT)
-        .module(frag_shader_module)
-        .name(b"main\0");
-
-    // Vertex Input State
-
-    let binding_descriptions = &[Vertex::binding_description()];
-    let attribute_descriptions = Vertex::attribute_descriptions();
-    let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
-        .vertex_binding_descriptions(binding_descriptions)
-        .vertex_attribute_descriptions(&attribute_descriptions);
-
-    // Input Assembly State
-
-    let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
-        .topology(vk::PrimitiveTopology::TRIANGLE_LIST)
-        .primitive_restart_enable(false);
-
-    // Viewport State
-
-    let viewport = vk::Viewport::builder()
-        .x(0.0)
-        .y(0.0)
-        .width(data.swapchain_extent.width as f32)
-        .height(data.swapchain_extent.height as f32)
-        .min_depth(0.0)
-        .max_depth(1.0);
-
-    let scissor = vk::Rect2D::builder()
-        .offset(vk::Offset2D { x: 0, y: 0 })
-        .extent(data.swapchain_extent);
-
-    let viewports = &[viewport];
-    let scissors = &[scissor];
-    let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
-        .viewports(viewports)
-        .scissors(scissors);
-
-    // Rasterization State
-
-    let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
-        .depth_clamp_enable(false)
-        .rasterizer_discard_enable(false)
-        .polygon_mode(vk::PolygonMode::FILL)
-        .line_width(1.0)
-        .cull_mode(vk::CullModeFlags::BACK)
-        .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
-        .depth_bias_enable(false);
-
-    // Multisample State
-
-    let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
-        .sample_shading_enable(true)
-        .min_sample_shading(0.2)
-        .rasterization_samples(data.msaa_samples);
-
-    // Depth Stencil State
-
-    let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
-        .depth_test_enable(true)
-        .depth_write_enable(true)
-        .depth_compare_op(vk::CompareOp::LESS)
-        .depth_bounds_test_enable(false)
-        .stencil_test_enable(false);
-
-    // Color Blend State
-
-    let attachment = vk::PipelineColorBlendAttachmentState::builder()
-        .color_write_mask(vk::ColorComponentFlags::all())
-        .blend_enable(true)
-        .src_color_blend_factor(vk::BlendFactor::SRC_ALPHA)
-        .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC_ALPHA)
-        .color_blend_op(vk::BlendOp::ADD)
-        .src_alpha_blend_factor(vk::BlendFactor::ONE)
-        .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
-        .alpha_blend_op(vk::BlendOp::ADD);
-
-    let attachments = &[attachment];
-    let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
-        .logic_op_enable(false)
-        .logic_op(vk::LogicOp::COPY)
-        .attachments(attachments)
-        .blend_constants([0.0, 0.0, 0.0, 0.0]);
-
-    // Push Constant Ranges
-
-    let vert_push_constant_range = vk::PushConstantRange::builder()
-        .stage_flags(vk::ShaderStageFlags::VERTEX)
-        .offset(0)
-        .size(64);
-
-    let frag_push_constant_range = vk::PushConstantRange::builder()
-        .stage_flags(vk::ShaderStageFlags::FRAGMENT)
-        .offset(64)
-        .size(4);
-
-    // Layout
-
-    let set_layouts = &[data.descriptor_set_layout];
-    let push_constant_ranges = &[vert_push_constant_range, frag_push_constant_range];
-    let layout_info = vk::PipelineLayoutCreateInfo::builder()
-        .set_layouts(set_layouts)
-        .push_constant_ranges(push_constant_ranges);
-
-    data.pipeline_layout = device.create_pipeline_layout(&layout_info, None).unwrap();
+// Creates the pipeline variants our models are drawn with, one per supported debug mode.
+pub unsafe fn create_pipelines(device: &Device, data: &mut AppData, near: f32, far: f32) -> Result<()> {
+    for mode in DebugMode::ALL {
+        if mode == DebugMode::Wireframe && !data.features.fill_mode_non_solid {
+            continue;
+        }
 
-    // Create
+        // The configured sample shading replaces that of the pipelines using sample shading.
+        let mut builder = mode.builder(near, far);
+        if builder.sample_shading.is_some() {
+            builder = builder.sample_shading(data.sample_shading);
+        }
+
+        // Every fragment shader converts its output to the color space of the swapchain.
+        builder = builder
+            .specialize(OUTPUT_CONSTANT_ID, data.swapchain_color_space as u32)
+            .specialize(PAPER_WHITE_CONSTANT_ID, data.paper_white.to_bits());
 
-    let stages = &[vert_stage, frag_stage];
-    let info = vk::GraphicsPipelineCreateInfo::builder()
-        .stages(stages)
-        .vertex_input_state(&vertex_input_state)
-        .input_assembly_state(&input_assembly_state)
-        .viewport_state(&viewport_state)
-        .rasterization_state(&rasterization_state)
-        .multisample_state(&multisample_state)
-        .depth_stencil_state(&depth_stencil_state)
-        .color_blend_state(&color_blend_state)
-        .layout(data.pipeline_layout)
-        .render_pass(data.render_pass)
-        .subpass(0);
-
-    data.pipeline = device
-        .create_graphics_pipelines(vk::PipelineCache::null(), &[info], None)
-        .unwrap()
-        .0[0];
-
-    // Cleanup
-
-    device.destroy_shader_module(vert_shader_module, None);
-    device.destroy_shader_module(frag_shader_module, None);
+        let pipeline = builder.build(device, data)?;
+        data.pipelines.insert(mode, pipeline);
+    }
 
     Ok(())
 }
 
 pub unsafe fn create_shader_module(device: &Device, bytecode: &[u8]) -> Result<vk::ShaderModule> {
-    let bytecode = Bytecode::new(bytecode).unwrap();
+    let bytecode = Bytecode::new(bytecode)?;
 
     let info = vk::ShaderModuleCreateInfo::builder()
         .code_size(bytecode.code_size())
         .code(bytecode.code());
 
-    Ok(device.create_shader_module(&info, None).unwrap())
+    Ok(device.create_shader_module(&info, None)?)
 }
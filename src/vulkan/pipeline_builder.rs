@@ -0,0 +1,424 @@
+use super::{
+    pipeline::create_shader_module,
+    reflection::reflect_pipeline,
+    shaders::{FRAGMENT_SHADER, VERTEX_SHADER},
+    structures::{AppData, PushConstants},
+    vertex::{VertexInput, VertexLayout},
+};
+use anyhow::{anyhow, Result};
+use std::{collections::HashSet, mem::size_of};
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Pipeline Builder
+//================================================
+
+// How the colors written by a pipeline are combined with the colors already in the color attachment.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlendPreset {
+    // Overwrites the existing colors.
+    #[default]
+    Opaque,
+    // Mixes with the existing colors by the written alpha.
+    Alpha,
+    // Mixes with the existing colors by the written alpha, whose colors are already multiplied by it.
+    Premultiplied,
+    // Adds to the existing colors.
+    Additive,
+}
+
+impl BlendPreset {
+    fn attachment(self) -> vk::PipelineColorBlendAttachmentState {
+        let (enable, src, dst) = match self {
+            Self::Opaque => (false, vk::BlendFactor::ONE, vk::BlendFactor::ZERO),
+            Self::Alpha => (true, vk::BlendFactor::SRC_ALPHA, vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+            Self::Premultiplied => (true, vk::BlendFactor::ONE, vk::BlendFactor::ONE_MINUS_SRC_ALPHA),
+            Self::Additive => (true, vk::BlendFactor::SRC_ALPHA, vk::BlendFactor::ONE),
+        };
+
+        vk::PipelineColorBlendAttachmentState::builder()
+            .color_write_mask(vk::ColorComponentFlags::all())
+            .blend_enable(enable)
+            .src_color_blend_factor(src)
+            .dst_color_blend_factor(dst)
+            .color_blend_op(vk::BlendOp::ADD)
+            .src_alpha_blend_factor(vk::BlendFactor::ONE)
+            .dst_alpha_blend_factor(vk::BlendFactor::ZERO)
+            .alpha_blend_op(vk::BlendOp::ADD)
+            .build()
+    }
+}
+
+// A graphics pipeline and the layout it was created with.
+#[derive(Clone, Debug, Default)]
+pub struct Pipeline {
+    pub pipeline: vk::Pipeline,
+    pub layout: vk::PipelineLayout,
+    // The push constant ranges of the layout, one per group of stages reading overlapping push constants.
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    // The settings the pipeline was built with (e.g., to rebuild it when its shaders change).
+    pub builder: PipelineBuilder,
+}
+
+impl Pipeline {
+    pub unsafe fn destroy(&self, device: &Device) {
+        device.destroy_pipeline(self.pipeline, None);
+        device.destroy_pipeline_layout(self.layout, None);
+    }
+}
+
+// The settings of a graphics pipeline for the render pass of our Vulkan app.
+//
+// The stage of each shader is found by reflecting it, as are the pipeline layout's push constant ranges.
+#[derive(Clone, Debug)]
+pub struct PipelineBuilder {
+    // The names of the shaders in `AppData::shaders`.
+    pub shaders: Vec<String>,
+    pub vertex_input: VertexInput,
+    pub topology: vk::PrimitiveTopology,
+    pub polygon_mode: vk::PolygonMode,
+    pub cull_mode: vk::CullModeFlags,
+    pub front_face: vk::FrontFace,
+    pub depth_test: bool,
+    pub depth_write: bool,
+    pub depth_compare: vk::CompareOp,
+    pub blend: BlendPreset,
+    // The number of samples per pixel (the MSAA sample count of our Vulkan app if `None`).
+    pub samples: Option<vk::SampleCountFlags>,
+    // The minimum fraction of samples shaded individually (no sample shading if `None` or unsupported).
+    pub sample_shading: Option<f32>,
+    // The values of 32-bit specialization constants by constant ID, shared by every stage.
+    pub specialization: Vec<(u32, u32)>,
+}
+
+impl Default for PipelineBuilder {
+    fn default() -> Self {
+        Self {
+            shaders: vec![],
+            vertex_input: VertexInput::new(),
+            topology: vk::PrimitiveTopology::TRIANGLE_LIST,
+            polygon_mode: vk::PolygonMode::FILL,
+            cull_mode: vk::CullModeFlags::BACK,
+            front_face: vk::FrontFace::COUNTER_CLOCKWISE,
+            depth_test: true,
+            depth_write: true,
+            depth_compare: vk::CompareOp::LESS,
+            blend: BlendPreset::Opaque,
+            samples: None,
+            sample_shading: None,
+            specialization: vec![],
+        }
+    }
+}
+
+impl PipelineBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //================================================
+    // Presets
+    //================================================
+
+    // Lit, textured models blended by their opacity (the pipeline our models are drawn with).
+    pub fn model() -> Self {
+        Self::new()
+            .shader(VERTEX_SHADER)
+            .shader(FRAGMENT_SHADER)
+            .blend(BlendPreset::Alpha)
+            .sample_shading(Some(0.2))
+    }
+
+    // Lit, textured models that hide everything behind them regardless of their opacity.
+    pub fn opaque_model() -> Self {
+        Self::model().blend(BlendPreset::Opaque)
+    }
+
+    // Lit, textured models drawn on top of everything else without affecting the depth buffer.
+    pub fn overlay_model() -> Self {
+        Self::model()
+            .depth_test(false)
+            .depth_write(false)
+            .cull_mode(vk::CullModeFlags::NONE)
+    }
+
+    //================================================
+    // Settings
+    //================================================
+
+    pub fn shader(mut self, name: &str) -> Self {
+        self.shaders.push(name.to_string());
+        self
+    }
+
+    // Adds a vertex buffer binding for vertices (or instances) of type `V`.
+    pub fn vertex<V: VertexLayout>(mut self) -> Self {
+        self.vertex_input = self.vertex_input.with::<V>();
+        self
+    }
+
+    pub fn topology(mut self, topology: vk::PrimitiveTopology) -> Self {
+        self.topology = topology;
+        self
+    }
+
+    pub fn polygon_mode(mut self, polygon_mode: vk::PolygonMode) -> Self {
+        self.polygon_mode = polygon_mode;
+        self
+    }
+
+    pub fn cull_mode(mut self, cull_mode: vk::CullModeFlags) -> Self {
+        self.cull_mode = cull_mode;
+        self
+    }
+
+    pub fn front_face(mut self, front_face: vk::FrontFace) -> Self {
+        self.front_face = front_face;
+        self
+    }
+
+    pub fn depth_test(mut self, depth_test: bool) -> Self {
+        self.depth_test = depth_test;
+        self
+    }
+
+    pub fn depth_write(mut self, depth_write: bool) -> Self {
+        self.depth_write = depth_write;
+        self
+    }
+
+    pub fn depth_compare(mut self, depth_compare: vk::CompareOp) -> Self {
+        self.depth_compare = depth_compare;
+        self
+    }
+
+    pub fn blend(mut self, blend: BlendPreset) -> Self {
+        self.blend = blend;
+        self
+    }
+
+    pub fn samples(mut self, samples: vk::SampleCountFlags) -> Self {
+        self.samples = Some(samples);
+        self
+    }
+
+    pub fn sample_shading(mut self, sample_shading: Option<f32>) -> Self {
+        self.sample_shading = sample_shading;
+        self
+    }
+
+    // Sets a 32-bit specialization constant (e.g., `f32::to_bits` for a `float` constant).
+    pub fn specialize(mut self, id: u32, value: u32) -> Self {
+        self.specialization.retain(|(i, _)| *i != id);
+        self.specialization.push((id, value));
+        self
+    }
+
+    // Whether the pipeline uses any of the named shaders.
+    pub fn uses_any(&self, shaders: &HashSet<String>) -> bool {
+        self.shaders.iter().any(|s| shaders.contains(s))
+    }
+
+    //================================================
+    // Build
+    //================================================
+
+    pub unsafe fn build(&self, device: &Device, data: &AppData) -> Result<Pipeline> {
+        // Reflection
+
+        let code = self
+            .shaders
+            .iter()
+            .map(|name| {
+                data.shaders
+                    .get(name)
+                    .map(|c| &c[..])
+                    .ok_or_else(|| anyhow!("Shader `{}` has not been loaded.", name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let reflection = reflect_pipeline(&code, &self.specialization)?;
+
+        // The descriptor set layout is shared by every pipeline and can't change without recreating the descriptor sets.
+        let bindings = &reflection.bindings;
+        if let Some(binding) = bindings
+            .iter()
+            .find(|b| !data.descriptor_bindings.iter().any(|l| b.is_compatible(l)))
+        {
+            return Err(anyhow!(
+                "The descriptors declared by the shaders changed (restart to apply):\n{:#?}",
+                binding
+            ));
+        }
+
+        reflection.check_push_constants(size_of::<PushConstants>())?;
+
+        // Vertex Input State
+
+        self.vertex_input.validate()?;
+        reflection.check_vertex_input(&self.vertex_input)?;
+
+        let vertex_input_state = vk::PipelineVertexInputStateCreateInfo::builder()
+            .vertex_binding_descriptions(&self.vertex_input.bindings)
+            .vertex_attribute_descriptions(&self.vertex_input.attributes);
+
+        // Input Assembly State
+
+        let input_assembly_state = vk::PipelineInputAssemblyStateCreateInfo::builder()
+            .topology(self.topology)
+            .primitive_restart_enable(false);
+
+        // Viewport State
+
+        // The viewport and scissor are dynamic (see below) so the pipeline doesn't depend on the swapchain extent.
+        let viewport_state = vk::PipelineViewportStateCreateInfo::builder()
+            .viewport_count(1)
+            .scissor_count(1);
+
+        // Rasterization State
+
+        let rasterization_state = vk::PipelineRasterizationStateCreateInfo::builder()
+            .depth_clamp_enable(false)
+            .rasterizer_discard_enable(false)
+            .polygon_mode(self.polygon_mode)
+            .line_width(1.0)
+            .cull_mode(self.cull_mode)
+            .front_face(self.front_face)
+            .depth_bias_enable(false);
+
+        // Multisample State
+
+        // Sample shading is optional.
+        let sample_shading = self.sample_shading.filter(|_| data.features.sample_rate_shading);
+
+        let multisample_state = vk::PipelineMultisampleStateCreateInfo::builder()
+            .sample_shading_enable(sample_shading.is_some())
+            .min_sample_shading(sample_shading.unwrap_or(0.0))
+            .rasterization_samples(self.samples.unwrap_or(data.msaa_samples));
+
+        // Depth Stencil State
+
+        let depth_stencil_state = vk::PipelineDepthStencilStateCreateInfo::builder()
+            .depth_test_enable(self.depth_test)
+            .depth_write_enable(self.depth_write)
+            .depth_compare_op(self.depth_compare)
+            .depth_bounds_test_enable(false)
+            .stencil_test_enable(false);
+
+        // Color Blend State
+
+        let attachments = &[self.blend.attachment()];
+        let color_blend_state = vk::PipelineColorBlendStateCreateInfo::builder()
+            .logic_op_enable(false)
+            .logic_op(vk::LogicOp::COPY)
+            .attachments(attachments)
+            .blend_constants([0.0, 0.0, 0.0, 0.0]);
+
+        // Dynamic State
+
+        let dynamic_states = &[vk::DynamicState::VIEWPORT, vk::DynamicState::SCISSOR];
+        let dynamic_state = vk::PipelineDynamicStateCreateInfo::builder().dynamic_states(dynamic_states);
+
+        // Layout
+
+        let set_layouts = &[data.descriptor_set_layout];
+        let layout_info = vk::PipelineLayoutCreateInfo::builder()
+            .set_layouts(set_layouts)
+            .push_constant_ranges(&reflection.push_constant_ranges);
+
+        let layout = device.create_pipeline_layout(&layout_info, None)?;
+
+        // Stages
+
+        let mut modules = vec![];
+        for code in &code {
+            match create_shader_module(device, code) {
+                Ok(module) => modules.push(module),
+                Err(e) => {
+                    modules.iter().for_each(|m| device.destroy_shader_module(*m, None));
+                    device.destroy_pipeline_layout(layout, None);
+                    return Err(e);
+                }
+            }
+        }
+
+        let map_entries = self
+            .specialization
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _))| {
+                vk::SpecializationMapEntry::builder()
+                    .constant_id(*id)
+                    .offset((i * size_of::<u32>()) as u32)
+                    .size(size_of::<u32>())
+                    .build()
+            })
+            .collect::<Vec<_>>();
+        let specialization_data = self
+            .specialization
+            .iter()
+            .flat_map(|(_, v)| v.to_ne_bytes())
+            .collect::<Vec<_>>();
+        let specialization_info = vk::SpecializationInfo::builder()
+            .map_entries(&map_entries)
+            .data(&specialization_data);
+
+        let stages = modules
+            .iter()
+            .zip(&reflection.stages)
+            .map(|(module, stage)| {
+                vk::PipelineShaderStageCreateInfo::builder()
+                    .stage(*stage)
+                    .module(*module)
+                    .name(b"main\0")
+                    .specialization_info(&specialization_info)
+                    .build()
+            })
+            .collect::<Vec<_>>();
+
+        // Create
+
+        // With dynamic rendering, the formats of the attachments replace the render pass.
+        let color_attachment_formats = &[data.swapchain_format];
+        let mut rendering_info = vk::PipelineRenderingCreateInfo::builder()
+            .color_attachment_formats(color_attachment_formats)
+            .depth_attachment_format(data.depth_format);
+
+        let mut info = vk::GraphicsPipelineCreateInfo::builder()
+            .stages(&stages)
+            .vertex_input_state(&vertex_input_state)
+            .input_assembly_state(&input_assembly_state)
+            .viewport_state(&viewport_state)
+            .rasterization_state(&rasterization_state)
+            .multisample_state(&multisample_state)
+            .depth_stencil_state(&depth_stencil_state)
+            .color_blend_state(&color_blend_state)
+            .dynamic_state(&dynamic_state)
+            .layout(layout)
+            .render_pass(data.render_pass)
+            .subpass(0);
+
+        if data.features.dynamic_rendering.is_some() {
+            info = info.push_next(&mut rendering_info);
+        }
+
+        let result = device.create_graphics_pipelines(data.pipeline_cache, &[info], None);
+
+        // Cleanup
+
+        modules.iter().for_each(|m| device.destroy_shader_module(*m, None));
+
+        match result {
+            Ok((pipelines, _)) => Ok(Pipeline {
+                pipeline: pipelines[0],
+                layout,
+                push_constant_ranges: reflection.push_constant_ranges,
+                builder: self.clone(),
+            }),
+            Err(e) => {
+                device.destroy_pipeline_layout(layout, None);
+                Err(anyhow!(e))
+            }
+        }
+    }
+}
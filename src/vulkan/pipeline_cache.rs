@@ -0,0 +1,91 @@
+use super::structures::AppData;
+use anyhow::Result;
+use log::*;
+use std::{fs, io::ErrorKind, path::Path};
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Pipeline Cache
+//================================================
+
+// The size of the header at the start of pipeline cache data (`VkPipelineCacheHeaderVersionOne`).
+const HEADER_SIZE: usize = 32;
+
+// Creates the pipeline cache, filled with the cache file if it was saved by the same driver and device.
+pub unsafe fn create_pipeline_cache(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    path: &Path,
+) -> Result<()> {
+    data.pipeline_cache_path = path.to_path_buf();
+
+    let properties = instance.get_physical_device_properties(data.physical_device);
+
+    let initial_data = match fs::read(path) {
+        Ok(bytes) if is_compatible(&bytes, &properties) => {
+            info!("Loaded pipeline cache `{}` ({} bytes).", path.display(), bytes.len());
+            bytes
+        }
+        Ok(_) => {
+            warn!(
+                "Discarding pipeline cache `{}` saved by another driver or device.",
+                path.display()
+            );
+            vec![]
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => vec![],
+        Err(e) => {
+            warn!("Failed to read pipeline cache `{}`: {}", path.display(), e);
+            vec![]
+        }
+    };
+
+    let info = vk::PipelineCacheCreateInfo::builder().initial_data(&initial_data);
+
+    data.pipeline_cache = device.create_pipeline_cache(&info, None)?;
+
+    Ok(())
+}
+
+// Writes the pipeline cache to the cache file.
+pub unsafe fn save_pipeline_cache(device: &Device, data: &AppData) -> Result<()> {
+    let bytes = device.get_pipeline_cache_data(data.pipeline_cache)?;
+
+    let path = &data.pipeline_cache_path;
+    if let Some(directory) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        fs::create_dir_all(directory)?;
+    }
+
+    // Written to a temporary file first so a crash while saving can't leave a truncated cache behind.
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, &bytes)?;
+    fs::rename(&temporary, path)?;
+
+    info!("Saved pipeline cache `{}` ({} bytes).", path.display(), bytes.len());
+
+    Ok(())
+}
+
+// Whether pipeline cache data was created by the driver and device described by `properties`.
+fn is_compatible(bytes: &[u8], properties: &vk::PhysicalDeviceProperties) -> bool {
+    if bytes.len() < HEADER_SIZE {
+        return false;
+    }
+
+    let read =
+        |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+
+    let header_size = read(0) as usize;
+    let header_version = read(4);
+    let vendor_id = read(8);
+    let device_id = read(12);
+    let uuid = &bytes[16..32];
+
+    header_size >= HEADER_SIZE
+        && header_size <= bytes.len()
+        && header_version == vk::PipelineCacheHeaderVersion::ONE.as_raw() as u32
+        && vendor_id == properties.vendor_id
+        && device_id == properties.device_id
+        && uuid == &properties.pipeline_cache_uuid[..]
+}
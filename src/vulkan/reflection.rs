@@ -0,0 +1,1184 @@
+use super::vertex::VertexInput;
+use anyhow::{anyhow, Result};
+use std::collections::HashMap;
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// SPIR-V
+//================================================
+
+const MAGIC: u32 = 0x0723_0203;
+
+// Opcodes
+const OP_NAME: u32 = 5;
+const OP_MEMBER_NAME: u32 = 6;
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_SPEC_CONSTANT: u32 = 50;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+// Decorations
+const DECORATION_SPEC_ID: u32 = 1;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// Storage Classes
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+// Image Dimensions
+const DIM_BUFFER: u32 = 5;
+const DIM_SUBPASS_DATA: u32 = 6;
+
+#[derive(Clone, Debug)]
+enum Type {
+    Scalar(NumericType, u32),
+    Vector(u32, u32),
+    Matrix(u32, u32),
+    Image { dim: u32, sampled: u32 },
+    Sampler,
+    SampledImage,
+    Array(u32, u32),
+    RuntimeArray(u32),
+    Struct(Vec<u32>),
+    Pointer(u32, u32),
+}
+
+// The parts of a SPIR-V module needed to reflect its interface.
+#[derive(Debug, Default)]
+struct Module {
+    execution_model: Option<u32>,
+    names: HashMap<u32, String>,
+    member_names: HashMap<(u32, u32), String>,
+    decorations: HashMap<(u32, u32), u32>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+    types: HashMap<u32, Type>,
+    constants: HashMap<u32, u32>,
+    variables: Vec<(u32, u32, u32)>,
+}
+
+impl Module {
+    // Specialization constants take the value given for their constant ID in `specialization`, or else their default.
+    fn parse(code: &[u8], specialization: &[(u32, u32)]) -> Result<Self> {
+        if code.len() % 4 != 0 || code.len() < 20 {
+            return Err(anyhow!("Invalid SPIR-V (size {}).", code.len()));
+        }
+
+        let mut words = code
+            .chunks_exact(4)
+            .map(|w| u32::from_le_bytes([w[0], w[1], w[2], w[3]]))
+            .collect::<Vec<_>>();
+        if words[0] == MAGIC.swap_bytes() {
+            words.iter_mut().for_each(|w| *w = w.swap_bytes());
+        } else if words[0] != MAGIC {
+            return Err(anyhow!("Invalid SPIR-V (magic number {:#x}).", words[0]));
+        }
+
+        let mut module = Self::default();
+
+        let mut offset = 5;
+        while offset < words.len() {
+            let count = (words[offset] >> 16) as usize;
+            let opcode = words[offset] & 0xFFFF;
+            if count == 0 || offset + count > words.len() {
+                return Err(anyhow!("Invalid SPIR-V (truncated instruction at word {}).", offset));
+            }
+
+            let operands = &words[offset + 1..offset + count];
+            let operand = |i: usize| operands.get(i).copied().unwrap_or(0);
+            let rest = operands.get(1..).unwrap_or_default();
+            match opcode {
+                OP_NAME => {
+                    module.names.insert(operand(0), read_string(rest));
+                }
+                OP_MEMBER_NAME => {
+                    let name = read_string(operands.get(2..).unwrap_or_default());
+                    module.member_names.insert((operand(0), operand(1)), name);
+                }
+                OP_ENTRY_POINT if module.execution_model.is_none() => {
+                    module.execution_model = Some(operand(0));
+                }
+                OP_DECORATE => {
+                    module.decorations.insert((operand(0), operand(1)), operand(2));
+                }
+                OP_MEMBER_DECORATE => {
+                    let key = (operand(0), operand(1), operand(2));
+                    module.member_decorations.insert(key, operand(3));
+                }
+                OP_TYPE_INT => {
+                    let numeric_type = if operand(2) == 1 {
+                        NumericType::SInt
+                    } else {
+                        NumericType::UInt
+                    };
+                    module.types.insert(operand(0), Type::Scalar(numeric_type, operand(1)));
+                }
+                OP_TYPE_FLOAT => {
+                    module
+                        .types
+                        .insert(operand(0), Type::Scalar(NumericType::Float, operand(1)));
+                }
+                OP_TYPE_VECTOR => {
+                    module.types.insert(operand(0), Type::Vector(operand(1), operand(2)));
+                }
+                OP_TYPE_MATRIX => {
+                    module.types.insert(operand(0), Type::Matrix(operand(1), operand(2)));
+                }
+                OP_TYPE_IMAGE => {
+                    let image = Type::Image {
+                        dim: operand(2),
+                        sampled: operand(6),
+                    };
+                    module.types.insert(operand(0), image);
+                }
+                OP_TYPE_SAMPLER => {
+                    module.types.insert(operand(0), Type::Sampler);
+                }
+                OP_TYPE_SAMPLED_IMAGE => {
+                    module.types.insert(operand(0), Type::SampledImage);
+                }
+                OP_TYPE_ARRAY => {
+                    module.types.insert(operand(0), Type::Array(operand(1), operand(2)));
+                }
+                OP_TYPE_RUNTIME_ARRAY => {
+                    module.types.insert(operand(0), Type::RuntimeArray(operand(1)));
+                }
+                OP_TYPE_STRUCT => {
+                    module.types.insert(operand(0), Type::Struct(rest.to_vec()));
+                }
+                OP_TYPE_POINTER => {
+                    module.types.insert(operand(0), Type::Pointer(operand(1), operand(2)));
+                }
+                OP_CONSTANT => {
+                    module.constants.insert(operand(1), operand(2));
+                }
+                // Decorations come before constants, so the constant ID is already known.
+                OP_SPEC_CONSTANT => {
+                    let value = module
+                        .decorations
+                        .get(&(operand(1), DECORATION_SPEC_ID))
+                        .and_then(|id| specialization.iter().find(|(i, _)| i == id))
+                        .map_or(operand(2), |(_, v)| *v);
+                    module.constants.insert(operand(1), value);
+                }
+                OP_VARIABLE => {
+                    module.variables.push((operand(1), operand(0), operand(2)));
+                }
+                _ => {}
+            }
+
+            offset += count;
+        }
+
+        Ok(module)
+    }
+
+    fn get(&self, id: u32) -> Result<&Type> {
+        self.types
+            .get(&id)
+            .ok_or_else(|| anyhow!("Invalid SPIR-V (unknown type %{}).", id))
+    }
+
+    fn name(&self, id: u32) -> String {
+        self.names.get(&id).cloned().unwrap_or_default()
+    }
+
+    // The size of a type in bytes, as laid out in a buffer.
+    fn size(&self, id: u32) -> Result<u32> {
+        Ok(match self.get(id)? {
+            Type::Scalar(_, width) => width / 8,
+            Type::Vector(component, count) => self.size(*component)? * count,
+            Type::Matrix(column, count) => self.size(*column)? * count,
+            Type::Array(element, length) => {
+                let length = self.array_length(*length)?;
+                match self.decorations.get(&(id, DECORATION_ARRAY_STRIDE)) {
+                    Some(stride) => stride * length,
+                    None => self.size(*element)? * length,
+                }
+            }
+            Type::Struct(members) => {
+                let mut size = 0;
+                for (index, member) in members.iter().enumerate() {
+                    let offset = self.member_decoration(id, index, DECORATION_OFFSET).unwrap_or(0);
+                    size = size.max(offset + self.member_size(id, index, *member)?);
+                }
+                size
+            }
+            other => return Err(anyhow!("Type {:?} has no size.", other)),
+        })
+    }
+
+    // The size of a struct member, taking matrix strides into account (e.g., a std140 `mat3` has 16-byte columns).
+    fn member_size(&self, id: u32, index: usize, member: u32) -> Result<u32> {
+        match (
+            self.get(member)?,
+            self.member_decoration(id, index, DECORATION_MATRIX_STRIDE),
+        ) {
+            (Type::Matrix(_, columns), Some(stride)) => Ok(stride * columns),
+            _ => self.size(member),
+        }
+    }
+
+    // The length of an array type, which may be a specialization constant but not an expression of one.
+    fn array_length(&self, id: u32) -> Result<u32> {
+        self.constants.get(&id).copied().ok_or_else(|| {
+            anyhow!(
+                "Array length %{} is not a constant (specialization constant expressions are unsupported).",
+                id
+            )
+        })
+    }
+
+    // The members of a struct, in declaration order.
+    fn members(&self, id: u32) -> Result<Vec<BlockMember>> {
+        let Type::Struct(members) = self.get(id)? else {
+            return Ok(vec![]);
+        };
+
+        members
+            .iter()
+            .enumerate()
+            .map(|(index, member)| {
+                Ok(BlockMember {
+                    name: self.member_names.get(&(id, index as u32)).cloned().unwrap_or_default(),
+                    offset: self.member_decoration(id, index, DECORATION_OFFSET).unwrap_or(0),
+                    size: self.member_size(id, index, *member)?,
+                })
+            })
+            .collect()
+    }
+
+    fn member_decoration(&self, id: u32, index: usize, decoration: u32) -> Option<u32> {
+        self.member_decorations.get(&(id, index as u32, decoration)).copied()
+    }
+
+    // The byte range covered by the members of a struct (e.g., `layout(offset = 64) float opacity` is 64..68).
+    fn member_range(&self, id: u32) -> Result<(u32, u32)> {
+        let Type::Struct(members) = self.get(id)? else {
+            return Err(anyhow!("Push constant block is not a struct."));
+        };
+
+        let mut start = u32::MAX;
+        let mut end = 0;
+        for (index, member) in members.iter().enumerate() {
+            let offset = self.member_decoration(id, index, DECORATION_OFFSET).unwrap_or(0);
+            start = start.min(offset);
+            end = end.max(offset + self.member_size(id, index, *member)?);
+        }
+
+        Ok((start.min(end), end))
+    }
+}
+
+// Reads a nul-terminated string packed into words.
+fn read_string(words: &[u32]) -> String {
+    let bytes = words
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .take_while(|b| *b != 0)
+        .collect::<Vec<_>>();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+//================================================
+// Reflection
+//================================================
+
+// The kind of numbers a shader input is made of.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum NumericType {
+    Float,
+    SInt,
+    UInt,
+}
+
+// A member of a uniform or storage buffer block.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BlockMember {
+    pub name: String,
+    pub offset: u32,
+    pub size: u32,
+}
+
+// A descriptor used by one or more shader stages.
+#[derive(Clone, Debug)]
+pub struct DescriptorBinding {
+    pub name: String,
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: vk::DescriptorType,
+    pub count: u32,
+    pub stages: vk::ShaderStageFlags,
+    // The size and members of the block of a uniform or storage buffer (zero and empty for other descriptors).
+    pub size: u32,
+    pub members: Vec<BlockMember>,
+}
+
+impl DescriptorBinding {
+    // Whether a binding declared by a pipeline's shaders can use `layout`'s binding in a shared descriptor set layout.
+    pub fn is_compatible(&self, layout: &Self) -> bool {
+        (self.set, self.binding, self.descriptor_type, self.count, self.size)
+            == (
+                layout.set,
+                layout.binding,
+                layout.descriptor_type,
+                layout.count,
+                layout.size,
+            )
+            && layout.stages.contains(self.stages)
+    }
+}
+
+// A vertex shader input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ShaderInput {
+    pub name: String,
+    pub location: u32,
+    pub numeric_type: NumericType,
+    pub components: u32,
+}
+
+// The interface of a single shader stage.
+#[derive(Clone, Debug)]
+pub struct ShaderReflection {
+    pub stage: vk::ShaderStageFlags,
+    pub bindings: Vec<DescriptorBinding>,
+    // The byte range (start, end) of the push constant block read by the shader.
+    pub push_constants: Option<(u32, u32)>,
+    pub inputs: Vec<ShaderInput>,
+}
+
+// Parses the descriptor bindings, push constant block and inputs of a SPIR-V shader.
+//
+// Arrays sized by specialization constants use the values in `specialization` (by constant ID) or their defaults.
+pub fn reflect_shader(code: &[u8], specialization: &[(u32, u32)]) -> Result<ShaderReflection> {
+    let module = Module::parse(code, specialization)?;
+
+    let stage = match module.execution_model {
+        Some(0) => vk::ShaderStageFlags::VERTEX,
+        Some(1) => vk::ShaderStageFlags::TESSELLATION_CONTROL,
+        Some(2) => vk::ShaderStageFlags::TESSELLATION_EVALUATION,
+        Some(3) => vk::ShaderStageFlags::GEOMETRY,
+        Some(4) => vk::ShaderStageFlags::FRAGMENT,
+        Some(5) => vk::ShaderStageFlags::COMPUTE,
+        other => return Err(anyhow!("Unsupported shader execution model: {:?}", other)),
+    };
+
+    let mut reflection = ShaderReflection {
+        stage,
+        bindings: vec![],
+        push_constants: None,
+        inputs: vec![],
+    };
+
+    for (id, pointer, storage_class) in &module.variables {
+        let Type::Pointer(_, pointee) = module.get(*pointer)? else {
+            return Err(anyhow!("Invalid SPIR-V (variable %{} is not a pointer).", id));
+        };
+
+        match *storage_class {
+            STORAGE_UNIFORM_CONSTANT | STORAGE_UNIFORM | STORAGE_STORAGE_BUFFER => {
+                reflection
+                    .bindings
+                    .push(reflect_binding(&module, *id, *pointee, *storage_class, stage)?);
+            }
+            STORAGE_PUSH_CONSTANT => {
+                reflection.push_constants = Some(module.member_range(*pointee)?);
+            }
+            STORAGE_INPUT if stage == vk::ShaderStageFlags::VERTEX => {
+                if module.decorations.contains_key(&(*id, DECORATION_BUILT_IN)) {
+                    continue;
+                }
+
+                let location = module
+                    .decorations
+                    .get(&(*id, DECORATION_LOCATION))
+                    .copied()
+                    .ok_or_else(|| anyhow!("Vertex shader input `{}` has no location.", module.name(*id)))?;
+                reflection
+                    .inputs
+                    .extend(reflect_input(&module, *id, *pointee, location)?);
+            }
+            _ => {}
+        }
+    }
+
+    Ok(reflection)
+}
+
+fn reflect_binding(
+    module: &Module,
+    id: u32,
+    pointee: u32,
+    storage_class: u32,
+    stage: vk::ShaderStageFlags,
+) -> Result<DescriptorBinding> {
+    let mut name = module.name(id);
+
+    let (mut type_id, mut count) = (pointee, 1);
+    match module.get(pointee)? {
+        Type::Array(element, length) => {
+            type_id = *element;
+            count = module.array_length(*length)?;
+        }
+        Type::RuntimeArray(_) => return Err(anyhow!("Descriptor `{}` is an unsized array (unsupported).", name)),
+        _ => {}
+    }
+
+    let buffer_block = module.decorations.contains_key(&(type_id, DECORATION_BUFFER_BLOCK));
+    let descriptor_type = match (storage_class, module.get(type_id)?) {
+        (STORAGE_UNIFORM, Type::Struct(_)) if buffer_block => vk::DescriptorType::STORAGE_BUFFER,
+        (STORAGE_UNIFORM, Type::Struct(_)) => vk::DescriptorType::UNIFORM_BUFFER,
+        (STORAGE_STORAGE_BUFFER, Type::Struct(_)) => vk::DescriptorType::STORAGE_BUFFER,
+        (_, Type::SampledImage) => vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+        (_, Type::Sampler) => vk::DescriptorType::SAMPLER,
+        (
+            _,
+            Type::Image {
+                dim: DIM_SUBPASS_DATA, ..
+            },
+        ) => vk::DescriptorType::INPUT_ATTACHMENT,
+        (
+            _,
+            Type::Image {
+                dim: DIM_BUFFER,
+                sampled: 1,
+            },
+        ) => vk::DescriptorType::UNIFORM_TEXEL_BUFFER,
+        (_, Type::Image { dim: DIM_BUFFER, .. }) => vk::DescriptorType::STORAGE_TEXEL_BUFFER,
+        (_, Type::Image { sampled: 1, .. }) => vk::DescriptorType::SAMPLED_IMAGE,
+        (_, Type::Image { .. }) => vk::DescriptorType::STORAGE_IMAGE,
+        (_, other) => return Err(anyhow!("Descriptor `{}` has an unsupported type: {:?}", name, other)),
+    };
+
+    let size = match module.get(type_id)? {
+        Type::Struct(_) => module.size(type_id)?,
+        _ => 0,
+    };
+    let members = module.members(type_id)?;
+
+    // Blocks are named by their type (e.g., `UniformBufferObject`) if their instance is unnamed.
+    if name.is_empty() {
+        name = module.name(type_id);
+    }
+
+    Ok(DescriptorBinding {
+        name,
+        set: module
+            .decorations
+            .get(&(id, DECORATION_DESCRIPTOR_SET))
+            .copied()
+            .unwrap_or(0),
+        binding: module.decorations.get(&(id, DECORATION_BINDING)).copied().unwrap_or(0),
+        descriptor_type,
+        count,
+        stages: stage,
+        size,
+        members,
+    })
+}
+
+// Returns the locations occupied by a vertex shader input (one per column for matrices).
+fn reflect_input(module: &Module, id: u32, type_id: u32, location: u32) -> Result<Vec<ShaderInput>> {
+    let name = module.name(id);
+
+    let scalar = |component: u32| match module.get(component)? {
+        Type::Scalar(numeric_type, _) => Ok(*numeric_type),
+        other => Err(anyhow!(
+            "Vertex shader input `{}` has an unsupported type: {:?}",
+            name,
+            other
+        )),
+    };
+
+    let (numeric_type, components, columns) = match module.get(type_id)? {
+        Type::Scalar(numeric_type, _) => (*numeric_type, 1, 1),
+        Type::Vector(component, count) => (scalar(*component)?, *count, 1),
+        Type::Matrix(column, columns) => match module.get(*column)? {
+            Type::Vector(component, count) => (scalar(*component)?, *count, *columns),
+            other => {
+                return Err(anyhow!(
+                    "Vertex shader input `{}` has an unsupported type: {:?}",
+                    name,
+                    other
+                ))
+            }
+        },
+        other => {
+            return Err(anyhow!(
+                "Vertex shader input `{}` has an unsupported type: {:?}",
+                name,
+                other
+            ))
+        }
+    };
+
+    Ok((0..columns)
+        .map(|column| ShaderInput {
+            name: name.clone(),
+            location: location + column,
+            numeric_type,
+            components,
+        })
+        .collect())
+}
+
+//================================================
+// Pipeline Reflection
+//================================================
+
+// The combined interface of the shader stages of a pipeline.
+#[derive(Clone, Debug, Default)]
+pub struct PipelineReflection {
+    // The stage of each shader, in the order the shaders were passed.
+    pub stages: Vec<vk::ShaderStageFlags>,
+    // Sorted by set and binding.
+    pub bindings: Vec<DescriptorBinding>,
+    // Overlapping push constant blocks of different stages are merged into one range for both stages.
+    pub push_constant_ranges: Vec<vk::PushConstantRange>,
+    pub vertex_inputs: Vec<ShaderInput>,
+}
+
+// Reflects and combines the shader stages of a pipeline, specialized with the same constants.
+pub fn reflect_pipeline(shaders: &[&[u8]], specialization: &[(u32, u32)]) -> Result<PipelineReflection> {
+    let shaders = shaders
+        .iter()
+        .map(|s| reflect_shader(s, specialization))
+        .collect::<Result<Vec<_>>>()?;
+
+    // Bindings
+
+    let mut bindings: Vec<DescriptorBinding> = vec![];
+    for binding in shaders.iter().flat_map(|s| &s.bindings) {
+        let existing = bindings
+            .iter_mut()
+            .find(|b| (b.set, b.binding) == (binding.set, binding.binding));
+        match existing {
+            Some(existing)
+                if (existing.descriptor_type, existing.count) != (binding.descriptor_type, binding.count) =>
+            {
+                return Err(anyhow!(
+                    "Descriptor {}.{} is declared as {:?} (x{}) and {:?} (x{}) in different stages.",
+                    binding.set,
+                    binding.binding,
+                    existing.descriptor_type,
+                    existing.count,
+                    binding.descriptor_type,
+                    binding.count,
+                ));
+            }
+            Some(existing)
+                if existing
+                    .members
+                    .iter()
+                    .zip(&binding.members)
+                    .any(|(a, b)| (a.offset, a.size) != (b.offset, b.size)) =>
+            {
+                return Err(anyhow!(
+                    "Block {}.{} (`{}`) is laid out differently in different stages.",
+                    binding.set,
+                    binding.binding,
+                    binding.name,
+                ));
+            }
+            Some(existing) => {
+                existing.stages |= binding.stages;
+                existing.size = existing.size.max(binding.size);
+                if binding.members.len() > existing.members.len() {
+                    existing.members = binding.members.clone();
+                }
+            }
+            None => bindings.push(binding.clone()),
+        }
+    }
+
+    bindings.sort_by_key(|b| (b.set, b.binding));
+
+    // Push Constants
+
+    let mut blocks = shaders
+        .iter()
+        .filter_map(|s| s.push_constants.map(|(start, end)| (start, end, s.stage)))
+        .collect::<Vec<_>>();
+    blocks.sort_by_key(|(start, ..)| *start);
+
+    let mut ranges: Vec<(u32, u32, vk::ShaderStageFlags)> = vec![];
+    for (start, end, stage) in blocks {
+        match ranges.last_mut() {
+            Some(last) if start < last.1 => {
+                last.1 = last.1.max(end);
+                last.2 |= stage;
+            }
+            _ => ranges.push((start, end, stage)),
+        }
+    }
+
+    let push_constant_ranges = ranges
+        .into_iter()
+        .map(|(start, end, stages)| {
+            vk::PushConstantRange::builder()
+                .stage_flags(stages)
+                .offset(start)
+                .size(end - start)
+                .build()
+        })
+        .collect();
+
+    // Vertex Inputs
+
+    let vertex_inputs = shaders
+        .iter()
+        .filter(|s| s.stage == vk::ShaderStageFlags::VERTEX)
+        .flat_map(|s| s.inputs.clone())
+        .collect();
+
+    Ok(PipelineReflection {
+        stages: shaders.iter().map(|s| s.stage).collect(),
+        bindings,
+        push_constant_ranges,
+        vertex_inputs,
+    })
+}
+
+impl PipelineReflection {
+    // The bindings of the (only) descriptor set used by our pipelines.
+    pub fn descriptor_set_layout_bindings(&self) -> Result<Vec<vk::DescriptorSetLayoutBinding>> {
+        if let Some(binding) = self.bindings.iter().find(|b| b.set != 0) {
+            return Err(anyhow!(
+                "Descriptor `{}` uses set {} (only set 0 is supported).",
+                binding.name,
+                binding.set
+            ));
+        }
+
+        Ok(self
+            .bindings
+            .iter()
+            .map(|b| {
+                vk::DescriptorSetLayoutBinding::builder()
+                    .binding(b.binding)
+                    .descriptor_type(b.descriptor_type)
+                    .descriptor_count(b.count)
+                    .stage_flags(b.stages)
+                    .build()
+            })
+            .collect())
+    }
+
+    // Checks that a binding written by our app is declared with the expected type (and block size, for buffers).
+    pub fn check_binding(&self, binding: u32, descriptor_type: vk::DescriptorType, size: Option<usize>) -> Result<()> {
+        let declared = self
+            .bindings
+            .iter()
+            .find(|b| b.set == 0 && b.binding == binding)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Binding {} ({:?}) is not declared by the shaders.",
+                    binding,
+                    descriptor_type
+                )
+            })?;
+
+        if declared.descriptor_type != descriptor_type {
+            return Err(anyhow!(
+                "Binding {} (`{}`) is declared as {:?} but written as {:?}.",
+                binding,
+                declared.name,
+                declared.descriptor_type,
+                descriptor_type
+            ));
+        }
+
+        if let Some(size) = size {
+            if declared.size as usize != size {
+                return Err(anyhow!(
+                    "Binding {} (`{}`) is declared with {} bytes but the Rust type has {} bytes.",
+                    binding,
+                    declared.name,
+                    declared.size,
+                    size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Checks that the members of a buffer block are laid out like the `(name, offset, size)` fields of the Rust type
+    // written to it (unnamed members are matched by their position).
+    pub fn check_members(&self, binding: u32, fields: &[(&str, usize, usize)]) -> Result<()> {
+        let declared = self
+            .bindings
+            .iter()
+            .find(|b| b.set == 0 && b.binding == binding)
+            .ok_or_else(|| anyhow!("Binding {} is not declared by the shaders.", binding))?;
+
+        for (index, member) in declared.members.iter().enumerate() {
+            let field = if member.name.is_empty() {
+                fields.get(index)
+            } else {
+                fields.iter().find(|(name, ..)| *name == member.name)
+            };
+
+            let Some((name, offset, size)) = field else {
+                return Err(anyhow!(
+                    "Member `{}` of binding {} (`{}`) has no field in the Rust type.",
+                    member.name,
+                    binding,
+                    declared.name
+                ));
+            };
+
+            if (*offset, *size) != (member.offset as usize, member.size as usize) {
+                return Err(anyhow!(
+                    "Member `{}` of binding {} (`{}`) is declared at {}..{} but the Rust field `{}` is at {}..{}.",
+                    member.name,
+                    binding,
+                    declared.name,
+                    member.offset,
+                    member.offset + member.size,
+                    name,
+                    offset,
+                    offset + size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Checks that the push constant blocks fit into the push constants written by our app.
+    pub fn check_push_constants(&self, size: usize) -> Result<()> {
+        for range in &self.push_constant_ranges {
+            if (range.offset + range.size) as usize > size {
+                return Err(anyhow!(
+                    "Push constants {}..{} ({:?}) exceed the {} bytes of the Rust type.",
+                    range.offset,
+                    range.offset + range.size,
+                    range.stage_flags,
+                    size
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    // Checks that every vertex shader input is provided by a vertex attribute of the same type.
+    pub fn check_vertex_input(&self, vertex_input: &VertexInput) -> Result<()> {
+        for input in &self.vertex_inputs {
+            let attribute = vertex_input
+                .attributes
+                .iter()
+                .find(|a| a.location == input.location)
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Vertex shader input `{}` (location {}) has no vertex attribute.",
+                        input.name,
+                        input.location
+                    )
+                })?;
+
+            if format_components(attribute.format) != Some((input.numeric_type, input.components)) {
+                return Err(anyhow!(
+                    "Vertex shader input `{}` (location {}) is {:?} x{} but the vertex attribute is {:?}.",
+                    input.name,
+                    input.location,
+                    input.numeric_type,
+                    input.components,
+                    attribute.format
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// The numeric type and component count read by a shader from a vertex attribute format.
+fn format_components(format: vk::Format) -> Option<(NumericType, u32)> {
+    Some(match format {
+        vk::Format::R32_SFLOAT => (NumericType::Float, 1),
+        vk::Format::R32G32_SFLOAT => (NumericType::Float, 2),
+        vk::Format::R32G32B32_SFLOAT => (NumericType::Float, 3),
+        vk::Format::R32G32B32A32_SFLOAT => (NumericType::Float, 4),
+        vk::Format::R8G8B8A8_UNORM => (NumericType::Float, 4),
+        vk::Format::R32_SINT => (NumericType::SInt, 1),
+        vk::Format::R32G32_SINT => (NumericType::SInt, 2),
+        vk::Format::R32G32B32_SINT => (NumericType::SInt, 3),
+        vk::Format::R32G32B32A32_SINT => (NumericType::SInt, 4),
+        vk::Format::R32_UINT => (NumericType::UInt, 1),
+        vk::Format::R32G32_UINT => (NumericType::UInt, 2),
+        vk::Format::R32G32B32_UINT => (NumericType::UInt, 3),
+        vk::Format::R32G32B32A32_UINT => (NumericType::UInt, 4),
+        vk::Format::R16G16B16A16_UINT => (NumericType::UInt, 4),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VERTEX_SHADER: &str = r#"
+        #version 450
+
+        layout(binding = 0) uniform UniformBufferObject {
+            mat4 view;
+            mat4 proj;
+        } ubo;
+
+        layout(push_constant) uniform PushConstants {
+            mat4 model;
+        } pcs;
+
+        layout(location = 0) in vec3 inPosition;
+        layout(location = 1) in mat4 inTransform;
+        layout(location = 5) in ivec2 inIndices;
+
+        void main() {
+            vec4 position = inTransform * vec4(inPosition, float(inIndices.x));
+            gl_Position = ubo.proj * ubo.view * pcs.model * position;
+        }
+    "#;
+
+    const FRAGMENT_SHADER: &str = r#"
+        #version 450
+
+        layout(binding = 0) uniform UniformBufferObject {
+            mat4 view;
+            mat4 proj;
+        } ubo;
+
+        layout(binding = 1) uniform sampler2D texSampler;
+
+        layout(push_constant) uniform PushConstants {
+            layout(offset = 64) float opacity;
+        } pcs;
+
+        layout(location = 0) in vec2 fragTexCoord;
+
+        layout(location = 0) out vec4 outColor;
+
+        void main() {
+            outColor = texture(texSampler, fragTexCoord) * ubo.view[0] * pcs.opacity;
+        }
+    "#;
+
+    const ARRAY_SHADER: &str = r#"
+        #version 450
+
+        layout(binding = 2) uniform sampler2D textures[4];
+
+        layout(binding = 3) uniform Light {
+            vec4 color;
+        } lights[2];
+
+        layout(push_constant) uniform PushConstants {
+            mat4 model;
+            float opacity;
+        } pcs;
+
+        layout(location = 0) in vec2 fragTexCoord;
+
+        layout(location = 0) out vec4 outColor;
+
+        void main() {
+            vec4 color = texture(textures[1], fragTexCoord) * lights[0].color * lights[1].color;
+            outColor = pcs.model * color * pcs.opacity;
+        }
+    "#;
+
+    fn compile(source: &str, kind: shaderc::ShaderKind) -> Vec<u8> {
+        let compiler = shaderc::Compiler::new().unwrap();
+        let mut options = shaderc::CompileOptions::new().unwrap();
+        options.set_target_env(shaderc::TargetEnv::Vulkan, shaderc::EnvVersion::Vulkan1_0 as u32);
+        compiler
+            .compile_into_spirv(source, kind, "test.glsl", "main", Some(&options))
+            .unwrap()
+            .as_binary_u8()
+            .to_vec()
+    }
+
+    fn vertex() -> Vec<u8> {
+        compile(VERTEX_SHADER, shaderc::ShaderKind::Vertex)
+    }
+
+    fn fragment() -> Vec<u8> {
+        compile(FRAGMENT_SHADER, shaderc::ShaderKind::Fragment)
+    }
+
+    fn find(bindings: &[DescriptorBinding], binding: u32) -> &DescriptorBinding {
+        bindings.iter().find(|b| b.binding == binding).unwrap()
+    }
+
+    #[test]
+    fn uniform_buffer_and_sampler() {
+        let reflection = reflect_shader(&fragment(), &[]).unwrap();
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::FRAGMENT);
+        assert_eq!(reflection.bindings.len(), 2);
+
+        let ubo = find(&reflection.bindings, 0);
+        assert_eq!(ubo.name, "ubo");
+        assert_eq!(ubo.set, 0);
+        assert_eq!(ubo.descriptor_type, vk::DescriptorType::UNIFORM_BUFFER);
+        assert_eq!(ubo.count, 1);
+        assert_eq!(ubo.size, 128);
+
+        let sampler = find(&reflection.bindings, 1);
+        assert_eq!(sampler.name, "texSampler");
+        assert_eq!(sampler.descriptor_type, vk::DescriptorType::COMBINED_IMAGE_SAMPLER);
+        assert_eq!(sampler.count, 1);
+        assert_eq!(sampler.size, 0);
+    }
+
+    #[test]
+    fn vertex_inputs() {
+        let reflection = reflect_shader(&vertex(), &[]).unwrap();
+        assert_eq!(reflection.stage, vk::ShaderStageFlags::VERTEX);
+
+        let mut inputs = reflection
+            .inputs
+            .iter()
+            .map(|i| (i.location, i.numeric_type, i.components))
+            .collect::<Vec<_>>();
+        inputs.sort_by_key(|(location, ..)| *location);
+
+        // Matrices occupy one location per column.
+        assert_eq!(
+            inputs,
+            [
+                (0, NumericType::Float, 3),
+                (1, NumericType::Float, 4),
+                (2, NumericType::Float, 4),
+                (3, NumericType::Float, 4),
+                (4, NumericType::Float, 4),
+                (5, NumericType::SInt, 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn push_constants() {
+        assert_eq!(reflect_shader(&vertex(), &[]).unwrap().push_constants, Some((0, 64)));
+        assert_eq!(reflect_shader(&fragment(), &[]).unwrap().push_constants, Some((64, 68)));
+
+        // Adjacent blocks keep separate ranges.
+        let reflection = reflect_pipeline(&[&vertex(), &fragment()], &[]).unwrap();
+        let ranges = reflection
+            .push_constant_ranges
+            .iter()
+            .map(|r| (r.stage_flags, r.offset, r.size))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ranges,
+            [
+                (vk::ShaderStageFlags::VERTEX, 0, 64),
+                (vk::ShaderStageFlags::FRAGMENT, 64, 4),
+            ]
+        );
+
+        reflection.check_push_constants(68).unwrap();
+        assert!(reflection.check_push_constants(64).is_err());
+    }
+
+    #[test]
+    fn overlapping_push_constants() {
+        let array = compile(ARRAY_SHADER, shaderc::ShaderKind::Fragment);
+        let reflection = reflect_pipeline(&[&vertex(), &array], &[]).unwrap();
+        assert_eq!(reflection.push_constant_ranges.len(), 1);
+
+        let range = reflection.push_constant_ranges[0];
+        assert_eq!(
+            range.stage_flags,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT
+        );
+        assert_eq!((range.offset, range.size), (0, 68));
+    }
+
+    #[test]
+    fn arrays() {
+        let reflection = reflect_shader(&compile(ARRAY_SHADER, shaderc::ShaderKind::Fragment), &[]).unwrap();
+
+        let textures = find(&reflection.bindings, 2);
+        assert_eq!(textures.descriptor_type, vk::DescriptorType::COMBINED_IMAGE_SAMPLER);
+        assert_eq!(textures.count, 4);
+
+        let lights = find(&reflection.bindings, 3);
+        assert_eq!(lights.descriptor_type, vk::DescriptorType::UNIFORM_BUFFER);
+        assert_eq!(lights.count, 2);
+        assert_eq!(lights.size, 16);
+    }
+
+    #[test]
+    fn merged_stages() {
+        let reflection = reflect_pipeline(&[&vertex(), &fragment()], &[]).unwrap();
+        assert_eq!(
+            reflection.stages,
+            [vk::ShaderStageFlags::VERTEX, vk::ShaderStageFlags::FRAGMENT]
+        );
+
+        let bindings = reflection
+            .bindings
+            .iter()
+            .map(|b| (b.binding, b.descriptor_type, b.stages))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            bindings,
+            [
+                (
+                    0,
+                    vk::DescriptorType::UNIFORM_BUFFER,
+                    vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT
+                ),
+                (
+                    1,
+                    vk::DescriptorType::COMBINED_IMAGE_SAMPLER,
+                    vk::ShaderStageFlags::FRAGMENT
+                ),
+            ]
+        );
+
+        // Only the vertex shader's inputs are vertex inputs.
+        assert_eq!(reflection.vertex_inputs.len(), 6);
+
+        let layout = reflection.descriptor_set_layout_bindings().unwrap();
+        assert_eq!(layout.len(), 2);
+        assert_eq!(
+            layout[0].stage_flags,
+            vk::ShaderStageFlags::VERTEX | vk::ShaderStageFlags::FRAGMENT
+        );
+
+        reflection
+            .check_binding(0, vk::DescriptorType::UNIFORM_BUFFER, Some(128))
+            .unwrap();
+        assert!(reflection
+            .check_binding(0, vk::DescriptorType::UNIFORM_BUFFER, Some(64))
+            .is_err());
+        assert!(reflection
+            .check_binding(1, vk::DescriptorType::SAMPLED_IMAGE, None)
+            .is_err());
+    }
+
+    #[test]
+    fn conflicting_stages() {
+        let conflicting = compile(
+            r#"
+                #version 450
+
+                layout(binding = 0) uniform sampler2D texSampler;
+
+                layout(location = 0) out vec4 outColor;
+
+                void main() {
+                    outColor = texture(texSampler, vec2(0.0));
+                }
+            "#,
+            shaderc::ShaderKind::Fragment,
+        );
+
+        assert!(reflect_pipeline(&[&vertex(), &conflicting], &[]).is_err());
+    }
+
+    const SPECIALIZED_SHADER: &str = r#"
+        #version 450
+
+        layout(constant_id = 3) const int TEXTURES = 3;
+
+        layout(binding = 2) uniform sampler2D textures[TEXTURES];
+
+        layout(location = 0) in vec2 fragTexCoord;
+
+        layout(location = 0) out vec4 outColor;
+
+        void main() {
+            outColor = texture(textures[1], fragTexCoord);
+        }
+    "#;
+
+    #[test]
+    fn specialized_arrays() {
+        let code = compile(SPECIALIZED_SHADER, shaderc::ShaderKind::Fragment);
+
+        let default = reflect_shader(&code, &[]).unwrap();
+        assert_eq!(find(&default.bindings, 2).count, 3);
+
+        let specialized = reflect_shader(&code, &[(0, 7), (3, 5)]).unwrap();
+        assert_eq!(find(&specialized.bindings, 2).count, 5);
+
+        // Lengths computed from specialization constants can't be reflected.
+        let expression = compile(
+            &SPECIALIZED_SHADER.replace("textures[TEXTURES]", "textures[TEXTURES * 2]"),
+            shaderc::ShaderKind::Fragment,
+        );
+        assert!(reflect_shader(&expression, &[]).is_err());
+    }
+
+    #[test]
+    fn block_members() {
+        let reflection = reflect_pipeline(&[&vertex(), &fragment()], &[]).unwrap();
+        assert_eq!(
+            find(&reflection.bindings, 0).members,
+            [
+                BlockMember {
+                    name: "view".into(),
+                    offset: 0,
+                    size: 64
+                },
+                BlockMember {
+                    name: "proj".into(),
+                    offset: 64,
+                    size: 64
+                },
+            ]
+        );
+
+        reflection
+            .check_members(0, &[("view", 0, 64), ("proj", 64, 64)])
+            .unwrap();
+
+        // Swapped members have the same size but not the same offsets.
+        assert!(reflection
+            .check_members(0, &[("proj", 0, 64), ("view", 64, 64)])
+            .is_err());
+        assert!(reflection.check_members(0, &[("view", 0, 64)]).is_err());
+    }
+
+    #[test]
+    fn conflicting_block_layouts() {
+        let shifted = compile(
+            &FRAGMENT_SHADER.replace("mat4 view;", "layout(offset = 16) mat4 view;"),
+            shaderc::ShaderKind::Fragment,
+        );
+
+        assert!(reflect_pipeline(&[&vertex(), &shifted], &[]).is_err());
+    }
+
+    #[test]
+    fn invalid_spirv() {
+        assert!(reflect_shader(&[0; 20], &[]).is_err());
+        assert!(reflect_shader(&[0; 3], &[]).is_err());
+    }
+}
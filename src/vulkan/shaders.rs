@@ -0,0 +1,122 @@
+use super::structures::AppData;
+use anyhow::{anyhow, Result};
+use log::*;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::{
+    collections::HashSet,
+    fs,
+    path::Path,
+    sync::mpsc::{self, Receiver},
+};
+
+//================================================
+// Shaders
+//================================================
+
+// The GLSL shaders of the main pipeline, relative to the shader directory.
+pub const VERTEX_SHADER: &str = "shader.vert";
+pub const FRAGMENT_SHADER: &str = "shader.frag";
+// The fragment shader of the debug visualization pipelines (see `DebugMode`).
+pub const DEBUG_FRAGMENT_SHADER: &str = "debug.frag";
+
+// The extension of GLSL files that are only included by other shaders (e.g., `output.glsl`).
+pub const INCLUDE_EXTENSION: &str = ".glsl";
+
+// Compiles the shaders used by our pipelines from the shader directory.
+pub fn load_shaders(data: &mut AppData, directory: &Path) -> Result<()> {
+    data.shader_directory = directory.to_path_buf();
+    data.shaders.clear();
+
+    for name in [VERTEX_SHADER, FRAGMENT_SHADER, DEBUG_FRAGMENT_SHADER] {
+        let code = compile_shader(&directory.join(name))?;
+        data.shaders.insert(name.to_string(), code);
+    }
+
+    Ok(())
+}
+
+// Compiles a GLSL shader to SPIR-V, inferring its stage from its extension (e.g., `.vert` or `.frag`).
+pub fn compile_shader(path: &Path) -> Result<Vec<u8>> {
+    let kind = match path.extension().and_then(|e| e.to_str()).unwrap_or_default() {
+        "vert" => shaderc::ShaderKind::Vertex,
+        "frag" => shaderc::ShaderKind::Fragment,
+        "comp" => shaderc::ShaderKind::Compute,
+        "geom" => shaderc::ShaderKind::Geometry,
+        "tesc" => shaderc::ShaderKind::TessControl,
+        "tese" => shaderc::ShaderKind::TessEvaluation,
+        _ => return Err(anyhow!("Unknown shader stage for `{}`.", path.display())),
+    };
+
+    let source = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read shader `{}`: {}", path.display(), e))?;
+
+    let compiler = shaderc::Compiler::new().ok_or_else(|| anyhow!("Failed to create shader compiler."))?;
+    let mut options = shaderc::CompileOptions::new().ok_or_else(|| anyhow!("Failed to create shader options."))?;
+    options.set_target_env(shaderc::TargetEnv::Vulkan, shaderc::EnvVersion::Vulkan1_0 as u32);
+
+    // Includes (e.g., `#include "output.glsl"`) are resolved relative to the including shader.
+    let directory = path.parent().unwrap_or(Path::new("")).to_path_buf();
+    options.set_include_callback(move |name, _, _, _| {
+        let path = directory.join(name);
+        let content = fs::read_to_string(&path).map_err(|e| format!("Failed to read `{}`: {}", path.display(), e))?;
+        Ok(shaderc::ResolvedInclude {
+            resolved_name: path.display().to_string(),
+            content,
+        })
+    });
+
+    let name = path.display().to_string();
+    let artifact = compiler
+        .compile_into_spirv(&source, kind, &name, "main", Some(&options))
+        .map_err(|e| anyhow!("Failed to compile shader `{}`:\n{}", name, e))?;
+
+    if artifact.get_num_warnings() > 0 {
+        warn!(
+            "Compiled shader `{}` with warnings:\n{}",
+            name,
+            artifact.get_warning_messages()
+        );
+    }
+
+    Ok(artifact.as_binary_u8().to_vec())
+}
+
+//================================================
+// Hot Reload
+//================================================
+
+// Watches the shader directory for changed shaders.
+#[derive(Debug)]
+pub struct ShaderWatcher {
+    // Dropping the watcher stops watching.
+    _watcher: RecommendedWatcher,
+    events: Receiver<notify::Result<notify::Event>>,
+}
+
+impl ShaderWatcher {
+    pub fn new(directory: &Path) -> Result<Self> {
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        watcher.watch(directory, RecursiveMode::NonRecursive)?;
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    // Returns the names of the files changed since the last call.
+    pub fn changed(&self) -> HashSet<String> {
+        let mut changed = HashSet::new();
+        for event in self.events.try_iter() {
+            match event {
+                // Editors often save by replacing the file, so any event but a read counts as a change.
+                Ok(event) if !matches!(event.kind, EventKind::Access(_)) => {
+                    let names = event.paths.iter().filter_map(|p| p.file_name()?.to_str());
+                    changed.extend(names.map(|n| n.to_string()));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Failed to watch shaders: {}", e),
+            }
+        }
+        changed
+    }
+}
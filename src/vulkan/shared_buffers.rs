@@ -1,5 +1,5 @@
 use super::{
-    shared_other::{begin_single_time_commands, end_single_time_commands, get_memory_type_index},
+    shared_other::{begin_queue_commands, end_transfer_commands, get_memory_type_index, QueueFamily},
     structures::AppData,
 };
 use anyhow::Result;
@@ -48,12 +48,13 @@ pub unsafe fn copy_buffer(
     destination: vk::Buffer,
     size: vk::DeviceSize,
 ) -> Result<()> {
-    let command_buffer = begin_single_time_commands(device, data).unwrap();
+    let command_buffer = begin_queue_commands(device, data, QueueFamily::Transfer).unwrap();
 
     let regions = vk::BufferCopy::builder().size(size);
     device.cmd_copy_buffer(command_buffer, source, destination, &[regions]);
 
-    end_single_time_commands(device, data, command_buffer).unwrap();
+    // The destination is used by the graphics queue.
+    end_transfer_commands(device, data, command_buffer, &[destination], &[])?;
 
     Ok(())
 }
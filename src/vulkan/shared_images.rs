@@ -1,5 +1,5 @@
 use super::{
-    shared_other::{begin_single_time_commands, end_single_time_commands, get_memory_type_index},
+    shared_other::{begin_queue_commands, end_queue_commands, get_memory_type_index, QueueFamily},
     structures::AppData,
 };
 use anyhow::{anyhow, Result};
@@ -88,6 +88,29 @@ pub unsafe fn transition_image_layout(
     old_layout: vk::ImageLayout,
     new_layout: vk::ImageLayout,
     mip_levels: u32,
+) -> Result<()> {
+    // Images are uploaded on the transfer queue and sampled on the graphics queue.
+    let family = if new_layout == vk::ImageLayout::TRANSFER_DST_OPTIMAL {
+        QueueFamily::Transfer
+    } else {
+        QueueFamily::Graphics
+    };
+
+    let command_buffer = begin_queue_commands(device, data, family).unwrap();
+    record_image_layout_transition(device, command_buffer, image, old_layout, new_layout, mip_levels)?;
+    end_queue_commands(device, data, family, command_buffer).unwrap();
+
+    Ok(())
+}
+
+// Records an image layout transition into a command buffer.
+pub unsafe fn record_image_layout_transition(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    image: vk::Image,
+    old_layout: vk::ImageLayout,
+    new_layout: vk::ImageLayout,
+    mip_levels: u32,
 ) -> Result<()> {
     let (src_access_mask, dst_access_mask, src_stage_mask, dst_stage_mask) = match (old_layout, new_layout) {
         (vk::ImageLayout::UNDEFINED, vk::ImageLayout::TRANSFER_DST_OPTIMAL) => (
@@ -105,8 +128,6 @@ pub unsafe fn transition_image_layout(
         _ => return Err(anyhow!("Unsupported image layout transition!")),
     };
 
-    let command_buffer = begin_single_time_commands(device, data).unwrap();
-
     let subresource = vk::ImageSubresourceRange::builder()
         .aspect_mask(vk::ImageAspectFlags::COLOR)
         .base_mip_level(0)
@@ -134,48 +155,60 @@ pub unsafe fn transition_image_layout(
         &[barrier],
     );
 
-    end_single_time_commands(device, data, command_buffer).unwrap();
-
     Ok(())
 }
 
+// Records a copy of a buffer into the first mip level of an image.
 pub unsafe fn copy_buffer_to_image(
     device: &Device,
-    data: &AppData,
+    command_buffer: vk::CommandBuffer,
     buffer: vk::Buffer,
     image: vk::Image,
     width: u32,
     height: u32,
-) -> Result<()> {
-    let command_buffer = begin_single_time_commands(device, data).unwrap();
-
-    let subresource = vk::ImageSubresourceLayers::builder()
-        .aspect_mask(vk::ImageAspectFlags::COLOR)
-        .mip_level(0)
-        .base_array_layer(0)
-        .layer_count(1);
+) {
+    copy_buffer_to_mip_levels(device, command_buffer, buffer, image, &[(0, width, height)]);
+}
 
-    let region = vk::BufferImageCopy::builder()
-        .buffer_offset(0)
-        .buffer_row_length(0)
-        .buffer_image_height(0)
-        .image_subresource(subresource)
-        .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
-        .image_extent(vk::Extent3D {
-            width,
-            height,
-            depth: 1,
-        });
+// Records a copy of consecutive mip levels of an image out of a buffer, each given by its offset in the buffer and its
+// size.
+pub unsafe fn copy_buffer_to_mip_levels(
+    device: &Device,
+    command_buffer: vk::CommandBuffer,
+    buffer: vk::Buffer,
+    image: vk::Image,
+    levels: &[(vk::DeviceSize, u32, u32)],
+) {
+    let regions = levels
+        .iter()
+        .enumerate()
+        .map(|(level, (offset, width, height))| {
+            let subresource = vk::ImageSubresourceLayers::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .mip_level(level as u32)
+                .base_array_layer(0)
+                .layer_count(1);
+
+            vk::BufferImageCopy::builder()
+                .buffer_offset(*offset)
+                .buffer_row_length(0)
+                .buffer_image_height(0)
+                .image_subresource(subresource)
+                .image_offset(vk::Offset3D { x: 0, y: 0, z: 0 })
+                .image_extent(vk::Extent3D {
+                    width: *width,
+                    height: *height,
+                    depth: 1,
+                })
+                .build()
+        })
+        .collect::<Vec<_>>();
 
     device.cmd_copy_buffer_to_image(
         command_buffer,
         buffer,
         image,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        &[region],
+        &regions,
     );
-
-    end_single_time_commands(device, data, command_buffer).unwrap();
-
-    Ok(())
 }
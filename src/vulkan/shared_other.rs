@@ -22,12 +22,56 @@ pub unsafe fn get_memory_type_index(
         .ok_or_else(|| anyhow!("Failed to find suitable memory type."))
 }
 
+// The queues single time commands can be submitted to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QueueFamily {
+    Graphics,
+    // Falls back to the graphics queue if the device has no suitable dedicated transfer queue family.
+    Transfer,
+}
+
+impl QueueFamily {
+    pub fn index(self, data: &AppData) -> u32 {
+        match self {
+            Self::Graphics => data.queue_families.graphics,
+            Self::Transfer => data.queue_families.transfer,
+        }
+    }
+
+    fn queue(self, data: &AppData) -> vk::Queue {
+        match self {
+            Self::Graphics => data.graphics_queue,
+            Self::Transfer => data.transfer_queue,
+        }
+    }
+
+    fn command_pool(self, data: &AppData) -> vk::CommandPool {
+        match self {
+            Self::Graphics => data.command_pool,
+            Self::Transfer => data.transfer_command_pool,
+        }
+    }
+}
+
 pub unsafe fn begin_single_time_commands(device: &Device, data: &AppData) -> Result<vk::CommandBuffer> {
+    begin_queue_commands(device, data, QueueFamily::Graphics)
+}
+
+pub unsafe fn end_single_time_commands(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+) -> Result<()> {
+    end_queue_commands(device, data, QueueFamily::Graphics, command_buffer)
+}
+
+// Begins single time commands for one of our queues.
+pub unsafe fn begin_queue_commands(device: &Device, data: &AppData, family: QueueFamily) -> Result<vk::CommandBuffer> {
     // Allocate
 
     let info = vk::CommandBufferAllocateInfo::builder()
         .level(vk::CommandBufferLevel::PRIMARY)
-        .command_pool(data.command_pool)
+        .command_pool(family.command_pool(data))
         .command_buffer_count(1);
 
     let command_buffer = device.allocate_command_buffers(&info).unwrap()[0];
@@ -41,9 +85,11 @@ pub unsafe fn begin_single_time_commands(device: &Device, data: &AppData) -> Res
     Ok(command_buffer)
 }
 
-pub unsafe fn end_single_time_commands(
+// Submits single time commands begun with `begin_queue_commands` and waits for them to complete.
+pub unsafe fn end_queue_commands(
     device: &Device,
     data: &AppData,
+    family: QueueFamily,
     command_buffer: vk::CommandBuffer,
 ) -> Result<()> {
     // End
@@ -52,17 +98,157 @@ pub unsafe fn end_single_time_commands(
 
     // Submit
 
+    let queue = family.queue(data);
     let command_buffers = &[command_buffer];
     let info = vk::SubmitInfo::builder().command_buffers(command_buffers);
 
+    device.queue_submit(queue, &[info], vk::Fence::null()).unwrap();
+    device.queue_wait_idle(queue).unwrap();
+
+    // Cleanup
+
+    device.free_command_buffers(family.command_pool(data), &[command_buffer]);
+
+    Ok(())
+}
+
+// Submits single time commands begun on the transfer queue with `begin_queue_commands`, hands the exclusively owned
+// buffers and images (with their layouts and mip levels) they wrote over to the graphics queue and waits for both.
+//
+// The transfer commands end by releasing the resources and signal a semaphore the graphics queue waits on before
+// acquiring them, so only the graphics queue is waited on.
+pub unsafe fn end_transfer_commands(
+    device: &Device,
+    data: &AppData,
+    command_buffer: vk::CommandBuffer,
+    buffers: &[vk::Buffer],
+    images: &[(vk::Image, vk::ImageLayout, u32)],
+) -> Result<()> {
+    let (src_index, dst_index) = (QueueFamily::Transfer.index(data), QueueFamily::Graphics.index(data));
+    if src_index == dst_index {
+        return end_queue_commands(device, data, QueueFamily::Transfer, command_buffer);
+    }
+
+    let buffer_barriers = buffers
+        .iter()
+        .map(|b| {
+            vk::BufferMemoryBarrier::builder()
+                .src_queue_family_index(src_index)
+                .dst_queue_family_index(dst_index)
+                .buffer(*b)
+                .offset(0)
+                .size(vk::WHOLE_SIZE)
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    let image_barriers = images
+        .iter()
+        .map(|(image, layout, mip_levels)| {
+            let subresource = vk::ImageSubresourceRange::builder()
+                .aspect_mask(vk::ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(*mip_levels)
+                .base_array_layer(0)
+                .layer_count(1);
+
+            vk::ImageMemoryBarrier::builder()
+                .old_layout(*layout)
+                .new_layout(*layout)
+                .src_queue_family_index(src_index)
+                .dst_queue_family_index(dst_index)
+                .image(*image)
+                .subresource_range(subresource)
+                .build()
+        })
+        .collect::<Vec<_>>();
+
+    // Release
+
+    let release_buffer_barriers = buffer_barriers
+        .iter()
+        .map(|b| vk::BufferMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            ..*b
+        })
+        .collect::<Vec<_>>();
+    let release_image_barriers = image_barriers
+        .iter()
+        .map(|b| vk::ImageMemoryBarrier {
+            src_access_mask: vk::AccessFlags::TRANSFER_WRITE,
+            ..*b
+        })
+        .collect::<Vec<_>>();
+
+    device.cmd_pipeline_barrier(
+        command_buffer,
+        vk::PipelineStageFlags::TRANSFER,
+        vk::PipelineStageFlags::BOTTOM_OF_PIPE,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &release_buffer_barriers,
+        &release_image_barriers,
+    );
+
+    device.end_command_buffer(command_buffer).unwrap();
+
+    let semaphore = device
+        .create_semaphore(&vk::SemaphoreCreateInfo::builder(), None)
+        .unwrap();
+
+    let release_command_buffers = &[command_buffer];
+    let signal_semaphores = &[semaphore];
+    let info = vk::SubmitInfo::builder()
+        .command_buffers(release_command_buffers)
+        .signal_semaphores(signal_semaphores);
+
     device
-        .queue_submit(data.graphics_queue, &[info], vk::Fence::null())
+        .queue_submit(QueueFamily::Transfer.queue(data), &[info], vk::Fence::null())
         .unwrap();
-    device.queue_wait_idle(data.graphics_queue).unwrap();
+
+    // Acquire
+
+    let dst_access_mask = vk::AccessFlags::MEMORY_READ | vk::AccessFlags::MEMORY_WRITE;
+    let acquire_buffer_barriers = buffer_barriers
+        .iter()
+        .map(|b| vk::BufferMemoryBarrier { dst_access_mask, ..*b })
+        .collect::<Vec<_>>();
+    let acquire_image_barriers = image_barriers
+        .iter()
+        .map(|b| vk::ImageMemoryBarrier { dst_access_mask, ..*b })
+        .collect::<Vec<_>>();
+
+    let acquire_command_buffer = begin_queue_commands(device, data, QueueFamily::Graphics)?;
+    device.cmd_pipeline_barrier(
+        acquire_command_buffer,
+        vk::PipelineStageFlags::TOP_OF_PIPE,
+        vk::PipelineStageFlags::ALL_COMMANDS,
+        vk::DependencyFlags::empty(),
+        &[] as &[vk::MemoryBarrier],
+        &acquire_buffer_barriers,
+        &acquire_image_barriers,
+    );
+
+    device.end_command_buffer(acquire_command_buffer).unwrap();
+
+    let wait_semaphores = &[semaphore];
+    let wait_stages = &[vk::PipelineStageFlags::ALL_COMMANDS];
+    let acquire_command_buffers = &[acquire_command_buffer];
+    let info = vk::SubmitInfo::builder()
+        .wait_semaphores(wait_semaphores)
+        .wait_dst_stage_mask(wait_stages)
+        .command_buffers(acquire_command_buffers);
+
+    // The semaphore is only signaled once the transfer commands are complete, so they are complete as well.
+    let queue = QueueFamily::Graphics.queue(data);
+    device.queue_submit(queue, &[info], vk::Fence::null()).unwrap();
+    device.queue_wait_idle(queue).unwrap();
 
     // Cleanup
 
-    device.free_command_buffers(data.command_pool, &[command_buffer]);
+    device.destroy_semaphore(semaphore, None);
+    device.free_command_buffers(QueueFamily::Transfer.command_pool(data), &[command_buffer]);
+    device.free_command_buffers(QueueFamily::Graphics.command_pool(data), &[acquire_command_buffer]);
 
     Ok(())
 }
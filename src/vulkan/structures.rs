@@ -1,28 +1,44 @@
 use super::{
     buffers::{create_index_buffer, create_uniform_buffers, create_vertex_buffer},
+    camera::Camera,
     color_objects::create_color_objects,
+    color_space::OutputColorSpace,
     command_buffers::create_command_buffers,
-    command_pool::create_command_pools,
-    constants::{Mat4, Vec2, Vec3, MAX_FRAMES_IN_FLIGHT, VALIDATION_ENABLED},
+    command_pool::{create_command_pools, create_image_command_pools},
+    config::{Config, VSync},
+    constants::{Mat4, Vec2, Vec3, Vec4, MAX_FRAMES_IN_FLIGHT, VALIDATION_ENABLED},
+    debug_mode::DebugMode,
     depth_objects::create_depth_objects,
     descriptors::{create_descriptor_pool, create_descriptor_sets},
+    dynamic_rendering::{begin_rendering, end_rendering},
+    features::DeviceFeatures,
     framebuffers::create_framebuffers,
     instance::create_instance,
     logical_device::create_logical_device,
     model::load_model,
-    physical_device::pick_physical_device,
-    pipeline::{create_descriptor_set_layout, create_pipeline, create_render_pass},
-    swapchain::{create_swapchain, create_swapchain_image_views},
+    offscreen::{create_offscreen_objects, read_offscreen_image},
+    physical_device::{find_msaa_samples, pick_physical_device},
+    pipeline::{create_descriptor_set_layout, create_pipelines, create_render_pass},
+    pipeline_builder::Pipeline,
+    pipeline_cache::{create_pipeline_cache, save_pipeline_cache},
+    reflection::DescriptorBinding,
+    shaders::{compile_shader, load_shaders, ShaderWatcher, INCLUDE_EXTENSION},
+    swapchain::{create_swapchain, create_swapchain_image_views, retire_swapchain, RetiredSwapchain},
     sync_objects::create_sync_objects,
-    texture::{create_texture_image, create_texture_image_view, create_texture_sampler},
+    texture::{create_texture_sampler, create_textures},
+    vertex::vertex_layout,
 };
 use anyhow::{anyhow, Result};
-use cgmath::{point3, vec3, Deg};
+use cgmath::{vec3, Deg};
+use log::*;
 use std::{
+    collections::HashMap,
     hash::{Hash, Hasher},
-    mem::size_of,
+    mem::{self, size_of},
+    path::PathBuf,
     ptr::copy_nonoverlapping as memcpy,
-    time::Instant,
+    thread,
+    time::{Duration, Instant},
 };
 use thiserror::Error;
 use vulkanalia::{
@@ -39,47 +55,39 @@ pub struct Vertex {
     pub pos: Vec3,
     pub color: Vec3,
     pub tex_coord: Vec2,
+    // A zero normal marks a normal that still has to be generated.
+    pub normal: Vec3,
+    // The tangent and its handedness (`w`); a zero `w` marks a tangent that still has to be generated.
+    pub tangent: Vec4,
 }
 
 impl Vertex {
-    pub fn new(pos: Vec3, color: Vec3, tex_coord: Vec2) -> Self {
-        Self { pos, color, tex_coord }
-    }
-
-    pub fn binding_description() -> vk::VertexInputBindingDescription {
-        vk::VertexInputBindingDescription::builder()
-            .binding(0)
-            .stride(size_of::<Vertex>() as u32)
-            .input_rate(vk::VertexInputRate::VERTEX)
-            .build()
-    }
-
-    pub fn attribute_descriptions() -> [vk::VertexInputAttributeDescription; 3] {
-        let pos = vk::VertexInputAttributeDescription::builder()
-            .binding(0)
-            .location(0)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(0)
-            .build();
-        let color = vk::VertexInputAttributeDescription::builder()
-            .binding(0)
-            .location(1)
-            .format(vk::Format::R32G32B32_SFLOAT)
-            .offset(size_of::<Vec3>() as u32)
-            .build();
-        let tex_coord = vk::VertexInputAttributeDescription::builder()
-            .binding(0)
-            .location(2)
-            .format(vk::Format::R32G32_SFLOAT)
-            .offset((size_of::<Vec3>() + size_of::<Vec3>()) as u32)
-            .build();
-        [pos, color, tex_coord]
+    pub fn new(pos: Vec3, color: Vec3, tex_coord: Vec2, normal: Vec3, tangent: Vec4) -> Self {
+        Self {
+            pos,
+            color,
+            tex_coord,
+            normal,
+            tangent,
+        }
     }
 }
 
+vertex_layout!(Vertex {
+    pos,
+    color,
+    tex_coord,
+    normal,
+    tangent
+});
+
 impl PartialEq for Vertex {
     fn eq(&self, other: &Self) -> bool {
-        self.pos == other.pos && self.color == other.color && self.tex_coord == other.tex_coord
+        self.pos == other.pos
+            && self.color == other.color
+            && self.tex_coord == other.tex_coord
+            && self.normal == other.normal
+            && self.tangent == other.tangent
     }
 }
 
@@ -95,9 +103,72 @@ impl Hash for Vertex {
         self.color[2].to_bits().hash(state);
         self.tex_coord[0].to_bits().hash(state);
         self.tex_coord[1].to_bits().hash(state);
+        self.normal[0].to_bits().hash(state);
+        self.normal[1].to_bits().hash(state);
+        self.normal[2].to_bits().hash(state);
+        self.tangent[0].to_bits().hash(state);
+        self.tangent[1].to_bits().hash(state);
+        self.tangent[2].to_bits().hash(state);
+        self.tangent[3].to_bits().hash(state);
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    pub diffuse: Vec3,
+    // The diffuse texture, or `None` to use the configured texture (only for the default material of OBJ models).
+    pub diffuse_texture: Option<TextureSource>,
+    // The index of the loaded diffuse texture in `AppData::textures`.
+    pub texture: usize,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            name: String::from("default"),
+            diffuse: vec3(1.0, 1.0, 1.0),
+            diffuse_texture: None,
+            texture: 0,
+        }
+    }
+}
+
+// Where the pixels of a material texture come from.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum TextureSource {
+    // A PNG file.
+    File(PathBuf),
+    // An image already decoded by the model loader, indexing `AppData::decoded_images`.
+    Decoded(usize),
+    // A single white pixel, for untextured materials.
+    White,
+}
+
+// Tightly packed RGBA8 pixels decoded by the model loader.
+#[derive(Clone, Debug)]
+pub struct DecodedImage {
+    pub pixels: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+// A range of the index buffer drawn with a single material.
+#[derive(Copy, Clone, Debug)]
+pub struct Submesh {
+    pub first_index: u32,
+    pub index_count: u32,
+    pub material: usize,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Texture {
+    pub mip_levels: u32,
+    pub image: vk::Image,
+    pub image_memory: vk::DeviceMemory,
+    pub image_view: vk::ImageView,
+}
+
 #[derive(Clone, Debug)]
 pub struct SwapchainSupport {
     pub capabilities: vk::SurfaceCapabilitiesKHR,
@@ -128,10 +199,20 @@ pub struct UniformBufferObject {
     pub proj: Mat4,
 }
 
+// The push constants of a model; the ranges read by each stage are found by reflecting the shaders.
+#[repr(C)]
 #[derive(Copy, Clone, Debug)]
+pub struct PushConstants {
+    pub model: Mat4,
+    pub opacity: f32,
+}
+
+#[derive(Copy, Clone, Debug, Default)]
 pub struct QueueFamilyIndices {
     pub graphics: u32,
     pub present: u32,
+    // A dedicated family if the device has a suitable one, otherwise the graphics family.
+    pub transfer: u32,
 }
 
 impl QueueFamilyIndices {
@@ -144,18 +225,47 @@ impl QueueFamilyIndices {
             .map(|i| i as u32);
 
         let mut present = None;
-        for (index, properties) in properties.iter().enumerate() {
-            if instance
-                .get_physical_device_surface_support_khr(physical_device, index as u32, data.surface)
-                .unwrap()
-            {
-                present = Some(index as u32);
-                break;
+        if data.headless {
+            // Without a surface, the graphics queue family stands in for presentation.
+            present = graphics;
+        } else {
+            for (index, properties) in properties.iter().enumerate() {
+                if instance
+                    .get_physical_device_surface_support_khr(physical_device, index as u32, data.surface)
+                    .unwrap()
+                {
+                    present = Some(index as u32);
+                    break;
+                }
             }
         }
 
+        // Families without graphics (and without compute) are the ones meant for asynchronous transfers.
+        //
+        // Unlike graphics and compute families, transfer-only families may only allow copying coarse blocks of images
+        // (`min_image_transfer_granularity`), so only those that allow copying single texels (e.g., the 1x1 mip level
+        // of a texture) are used.
+        let find = |excluded: vk::QueueFlags| {
+            properties
+                .iter()
+                .position(|p| {
+                    let granularity = p.min_image_transfer_granularity;
+                    p.queue_flags.contains(vk::QueueFlags::TRANSFER)
+                        && !p.queue_flags.intersects(excluded)
+                        && (granularity.width, granularity.height, granularity.depth) == (1, 1, 1)
+                })
+                .map(|i| i as u32)
+        };
+
+        let transfer =
+            find(vk::QueueFlags::GRAPHICS | vk::QueueFlags::COMPUTE).or_else(|| find(vk::QueueFlags::GRAPHICS));
+
         if let (Some(graphics), Some(present)) = (graphics, present) {
-            Ok(Self { graphics, present })
+            Ok(Self {
+                graphics,
+                present,
+                transfer: transfer.unwrap_or(graphics),
+            })
         } else {
             Err(anyhow!(SuitabilityError("Missing required queue families.")))
         }
@@ -167,7 +277,7 @@ impl QueueFamilyIndices {
 pub struct SuitabilityError(pub &'static str);
 
 // Our Vulkan app.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub struct App {
     pub entry: Entry,
     pub instance: Instance,
@@ -176,32 +286,69 @@ pub struct App {
     pub frame: usize,
     pub resized: bool,
     pub start: Instant,
+    pub fixed_time: Option<f32>,
     pub models: usize,
+    pub camera: Camera,
+    pub debug_mode: DebugMode,
+    pub last_frame: Instant,
+    // The frame rate frames are capped at when presenting doesn't wait for the vertical blank.
+    pub fps_limit: Option<u32>,
+    pub shader_watcher: Option<ShaderWatcher>,
 }
 
 impl App {
     // Creates our Vulkan app.
-    pub unsafe fn create(window: &Window) -> Result<Self> {
+    pub unsafe fn create(window: &Window, config: &Config) -> Result<Self> {
+        Self::init(Some(window), config)
+    }
+
+    // Creates our Vulkan app without a window, rendering into an offscreen image of the configured size.
+    pub unsafe fn create_headless(config: &Config) -> Result<Self> {
+        Self::init(None, config)
+    }
+
+    unsafe fn init(window: Option<&Window>, config: &Config) -> Result<Self> {
         let loader = LibloadingLoader::new(LIBRARY).unwrap();
         let entry = Entry::new(loader).map_err(|b| anyhow!("{}", b)).unwrap();
-        let mut data = AppData::default();
+        let mut data = AppData {
+            headless: window.is_none(),
+            vsync: config.vsync,
+            output_color_space: config.color_space,
+            paper_white: config.paper_white,
+            ..Default::default()
+        };
         let instance = create_instance(window, &entry, &mut data).unwrap();
-        data.surface = vk_window::create_surface(&instance, &window, &window).unwrap();
-        pick_physical_device(&instance, &mut data).unwrap();
+        if let Some(window) = window {
+            data.surface = vk_window::create_surface(&instance, &window, &window).unwrap();
+        }
+        pick_physical_device(&instance, &mut data, config.device.as_deref())?;
+        if let Some(samples) = config.msaa {
+            data.msaa_samples = find_msaa_samples(&data, samples);
+        }
+        data.sample_shading = Some(config.sample_shading).filter(|f| *f > 0.0);
         let device = create_logical_device(&entry, &instance, &mut data).unwrap();
-        create_swapchain(window, &instance, &device, &mut data).unwrap();
+        create_pipeline_cache(&instance, &device, &mut data, &config.pipeline_cache).unwrap();
+        if let Some(window) = window {
+            create_swapchain(window, &instance, &device, &mut data, vk::SwapchainKHR::null()).unwrap();
+        } else {
+            let extent = vk::Extent2D::builder()
+                .width(config.width)
+                .height(config.height)
+                .build();
+            create_offscreen_objects(&instance, &device, &mut data, extent).unwrap();
+        }
         create_swapchain_image_views(&device, &mut data).unwrap();
         create_render_pass(&instance, &device, &mut data).unwrap();
-        create_descriptor_set_layout(&device, &mut data).unwrap();
-        create_pipeline(&device, &mut data).unwrap();
+        load_shaders(&mut data, &config.shaders)?;
+        create_descriptor_set_layout(&device, &mut data)?;
+        create_pipelines(&device, &mut data, config.near, config.far)?;
         create_command_pools(&instance, &device, &mut data).unwrap();
         create_color_objects(&instance, &device, &mut data).unwrap();
         create_depth_objects(&instance, &device, &mut data).unwrap();
         create_framebuffers(&device, &mut data).unwrap();
-        create_texture_image(&instance, &device, &mut data).unwrap();
-        create_texture_image_view(&device, &mut data).unwrap();
-        create_texture_sampler(&device, &mut data).unwrap();
-        load_model(&mut data).unwrap();
+        load_model(&mut data, &config.model, config.normals)?;
+        create_textures(&instance, &device, &mut data, &config.texture)?;
+        create_texture_sampler(&instance, &device, &mut data).unwrap();
         create_vertex_buffer(&instance, &device, &mut data).unwrap();
         create_index_buffer(&instance, &device, &mut data).unwrap();
         create_uniform_buffers(&instance, &device, &mut data).unwrap();
@@ -209,6 +356,17 @@ impl App {
         create_descriptor_sets(&device, &mut data).unwrap();
         create_command_buffers(&device, &mut data).unwrap();
         create_sync_objects(&device, &mut data).unwrap();
+
+        // Shaders are only reloaded while rendering to a window.
+        let shader_watcher = match window.map(|_| ShaderWatcher::new(&config.shaders)) {
+            Some(Ok(watcher)) => Some(watcher),
+            Some(Err(e)) => {
+                warn!("Failed to watch shaders in `{}`: {}", config.shaders.display(), e);
+                None
+            }
+            None => None,
+        };
+
         Ok(Self {
             entry,
             instance,
@@ -217,16 +375,32 @@ impl App {
             frame: 0,
             resized: false,
             start: Instant::now(),
+            fixed_time: None,
             models: 1,
+            camera: Camera::new(config),
+            debug_mode: DebugMode::Lit,
+            last_frame: Instant::now(),
+            fps_limit: config.fps_limit,
+            shader_watcher,
         })
     }
 
     // Renders a frame for our Vulkan app.
     pub unsafe fn render(&mut self, window: &Window) -> Result<()> {
+        self.limit_frame_rate();
+
+        let now = Instant::now();
+        self.camera.update((now - self.last_frame).as_secs_f32());
+        self.last_frame = now;
+
+        self.reload_shaders()?;
+
         let in_flight_fence = self.data.in_flight_fences[self.frame];
 
         self.device.wait_for_fences(&[in_flight_fence], true, u64::MAX).unwrap();
 
+        self.destroy_retired_swapchains(in_flight_fence);
+
         let result = self.device.acquire_next_image_khr(
             self.data.swapchain,
             u64::MAX,
@@ -287,6 +461,47 @@ impl App {
         Ok(())
     }
 
+    // Waits until the next frame is due if the frame rate is capped and not already limited by vertical sync.
+    pub fn limit_frame_rate(&self) {
+        let synchronized = matches!(
+            self.data.present_mode,
+            vk::PresentModeKHR::FIFO | vk::PresentModeKHR::FIFO_RELAXED
+        );
+
+        if let Some(fps) = self.fps_limit.filter(|_| !synchronized) {
+            let next_frame = self.last_frame + Duration::from_secs_f64(1.0 / fps as f64);
+            let now = Instant::now();
+            if next_frame > now {
+                thread::sleep(next_frame - now);
+            }
+        }
+    }
+
+    // Renders a frame into the offscreen image and returns its RGBA8 pixels.
+    pub unsafe fn render_offscreen(&mut self) -> Result<Vec<u8>> {
+        let in_flight_fence = self.data.in_flight_fences[self.frame];
+
+        self.device.wait_for_fences(&[in_flight_fence], true, u64::MAX).unwrap();
+
+        self.update_command_buffer(0).unwrap();
+        self.update_uniform_buffer(0).unwrap();
+
+        let command_buffers = &[self.data.command_buffers[0]];
+        let submit_info = vk::SubmitInfo::builder().command_buffers(command_buffers);
+
+        self.device.reset_fences(&[in_flight_fence]).unwrap();
+
+        self.device
+            .queue_submit(self.data.graphics_queue, &[submit_info], in_flight_fence)
+            .unwrap();
+
+        self.device.wait_for_fences(&[in_flight_fence], true, u64::MAX).unwrap();
+
+        self.frame = (self.frame + 1) % MAX_FRAMES_IN_FLIGHT;
+
+        read_offscreen_image(&self.instance, &self.device, &self.data)
+    }
+
     // Updates a command buffer for our Vulkan app.
     #[rustfmt::skip]
     pub unsafe fn update_command_buffer(&mut self, image_index: usize) -> Result<()> {
@@ -317,21 +532,29 @@ impl App {
             depth_stencil: vk::ClearDepthStencilValue { depth: 1.0, stencil: 0 },
         };
 
-        let clear_values = &[color_clear_value, depth_clear_value];
-        let info = vk::RenderPassBeginInfo::builder()
-            .render_pass(self.data.render_pass)
-            .framebuffer(self.data.framebuffers[image_index])
-            .render_area(render_area)
-            .clear_values(clear_values);
-
-        self.device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::SECONDARY_COMMAND_BUFFERS);
+        if self.data.features.dynamic_rendering.is_some() {
+            begin_rendering(&self.device, &self.data, command_buffer, image_index, color_clear_value, depth_clear_value);
+        } else {
+            let clear_values = &[color_clear_value, depth_clear_value];
+            let info = vk::RenderPassBeginInfo::builder()
+                .render_pass(self.data.render_pass)
+                .framebuffer(self.data.framebuffers[image_index])
+                .render_area(render_area)
+                .clear_values(clear_values);
+
+            self.device.cmd_begin_render_pass(command_buffer, &info, vk::SubpassContents::SECONDARY_COMMAND_BUFFERS);
+        }
 
         let secondary_command_buffers = (0..self.models)
             .map(|i| self.update_secondary_command_buffer(image_index, i))
             .collect::<Result<Vec<_>, _>>().unwrap();
         self.device.cmd_execute_commands(command_buffer, &secondary_command_buffers[..]);
 
-        self.device.cmd_end_render_pass(command_buffer);
+        if self.data.features.dynamic_rendering.is_some() {
+            end_rendering(&self.device, &self.data, command_buffer, image_index);
+        } else {
+            self.device.cmd_end_render_pass(command_buffer);
+        }
 
         self.device.end_command_buffer(command_buffer).unwrap();
 
@@ -365,24 +588,39 @@ impl App {
         let y = (((model_index % 2) as f32) * 2.5) - 1.25;
         let z = (((model_index / 2) as f32) * -2.0) + 1.0;
 
-        let time = self.start.elapsed().as_secs_f32();
+        let time = self.fixed_time.unwrap_or_else(|| self.start.elapsed().as_secs_f32());
 
         let model = Mat4::from_translation(vec3(0.0, y, z)) * Mat4::from_axis_angle(
             vec3(0.0, 0.0, 1.0),
             Deg(90.0) * time
         );
 
-        let model_bytes = std::slice::from_raw_parts(&model as *const Mat4 as *const u8, size_of::<Mat4>());
-
         let opacity = (model_index + 1) as f32 * 0.25;
-        let opacity_bytes = &opacity.to_ne_bytes()[..];
+
+        let push_constants = PushConstants { model, opacity };
+        let push_constants_bytes = std::slice::from_raw_parts(
+            &push_constants as *const PushConstants as *const u8,
+            size_of::<PushConstants>(),
+        );
 
         // Commands
 
-        let inheritance_info = vk::CommandBufferInheritanceInfo::builder()
-            .render_pass(self.data.render_pass)
-            .subpass(0)
-            .framebuffer(self.data.framebuffers[image_index]);
+        // With dynamic rendering, the formats of the attachments replace the render pass and framebuffer.
+        let color_attachment_formats = &[self.data.swapchain_format];
+        let mut rendering_info = vk::CommandBufferInheritanceRenderingInfo::builder()
+            .color_attachment_formats(color_attachment_formats)
+            .depth_attachment_format(self.data.depth_format)
+            .rasterization_samples(self.data.msaa_samples);
+
+        let mut inheritance_info = vk::CommandBufferInheritanceInfo::builder();
+        if self.data.features.dynamic_rendering.is_some() {
+            inheritance_info = inheritance_info.push_next(&mut rendering_info);
+        } else {
+            inheritance_info = inheritance_info
+                .render_pass(self.data.render_pass)
+                .subpass(0)
+                .framebuffer(self.data.framebuffers[image_index]);
+        }
 
         let info = vk::CommandBufferBeginInfo::builder()
             .flags(vk::CommandBufferUsageFlags::RENDER_PASS_CONTINUE)
@@ -390,32 +628,47 @@ impl App {
 
         self.device.begin_command_buffer(command_buffer, &info).unwrap();
 
-        self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, self.data.pipeline);
+        let pipeline = &self.data.pipelines[&self.debug_mode];
+        self.device.cmd_bind_pipeline(command_buffer, vk::PipelineBindPoint::GRAPHICS, pipeline.pipeline);
+
+        let viewport = vk::Viewport::builder()
+            .x(0.0)
+            .y(0.0)
+            .width(self.data.swapchain_extent.width as f32)
+            .height(self.data.swapchain_extent.height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0);
+
+        let scissor = vk::Rect2D::builder()
+            .offset(vk::Offset2D { x: 0, y: 0 })
+            .extent(self.data.swapchain_extent);
+
+        self.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+        self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
         self.device.cmd_bind_vertex_buffers(command_buffer, 0, &[self.data.vertex_buffer], &[0]);
-        self.device.cmd_bind_index_buffer(command_buffer, self.data.index_buffer, 0, vk::IndexType::UINT32);
-        self.device.cmd_bind_descriptor_sets(
-            command_buffer,
-            vk::PipelineBindPoint::GRAPHICS,
-            self.data.pipeline_layout,
-            0,
-            &[self.data.descriptor_sets[image_index]],
-            &[],
-        );
-        self.device.cmd_push_constants(
-            command_buffer,
-            self.data.pipeline_layout,
-            vk::ShaderStageFlags::VERTEX,
-            0,
-            model_bytes,
-        );
-        self.device.cmd_push_constants(
-            command_buffer,
-            self.data.pipeline_layout,
-            vk::ShaderStageFlags::FRAGMENT,
-            64,
-            opacity_bytes,
-        );
-        self.device.cmd_draw_indexed(command_buffer, self.data.indices.len() as u32, 1, 0, 0, 0);
+        self.device.cmd_bind_index_buffer(command_buffer, self.data.index_buffer, 0, self.data.index_type);
+        for range in &pipeline.push_constant_ranges {
+            let (start, end) = (range.offset as usize, (range.offset + range.size) as usize);
+            self.device.cmd_push_constants(
+                command_buffer,
+                pipeline.layout,
+                range.stage_flags,
+                range.offset,
+                &push_constants_bytes[start..end],
+            );
+        }
+
+        for submesh in &self.data.submeshes {
+            self.device.cmd_bind_descriptor_sets(
+                command_buffer,
+                vk::PipelineBindPoint::GRAPHICS,
+                pipeline.layout,
+                0,
+                &[self.data.descriptor_sets[image_index][submesh.material]],
+                &[],
+            );
+            self.device.cmd_draw_indexed(command_buffer, submesh.index_count, 1, submesh.first_index, 0, 0);
+        }
 
         self.device.end_command_buffer(command_buffer).unwrap();
 
@@ -426,11 +679,7 @@ impl App {
     pub unsafe fn update_uniform_buffer(&self, image_index: usize) -> Result<()> {
         // MVP
 
-        let view = Mat4::look_at_rh(
-            point3::<f32>(6.0, 0.0, 2.0),
-            point3::<f32>(0.0, 0.0, 0.0),
-            vec3(0.0, 0.0, 1.0),
-        );
+        let view = self.camera.view();
 
         #[rustfmt::skip]
         let correction = Mat4::new(
@@ -440,13 +689,8 @@ impl App {
             0.0,  0.0, 1.0 / 2.0, 1.0,
         );
 
-        let proj = correction
-            * cgmath::perspective(
-                Deg(45.0),
-                self.data.swapchain_extent.width as f32 / self.data.swapchain_extent.height as f32,
-                0.1,
-                10.0,
-            );
+        let aspect = self.data.swapchain_extent.width as f32 / self.data.swapchain_extent.height as f32;
+        let proj = correction * self.camera.projection(aspect);
 
         let ubo = UniformBufferObject { view, proj };
 
@@ -469,26 +713,138 @@ impl App {
         Ok(())
     }
 
+    // Switches the pipeline variant our models are drawn with.
+    pub fn set_debug_mode(&mut self, mode: DebugMode) {
+        if self.data.pipelines.contains_key(&mode) {
+            self.debug_mode = mode;
+            info!("Debug mode: {:?}", mode);
+        } else {
+            warn!("Debug mode {:?} is not supported by this device.", mode);
+        }
+    }
+
+    // Recompiles the changed shaders and rebuilds the pipelines that use them.
+    //
+    // If a shader fails to compile or a pipeline fails to build, the error is logged and the old pipeline is kept.
+    pub unsafe fn reload_shaders(&mut self) -> Result<()> {
+        let Some(watcher) = &self.shader_watcher else {
+            return Ok(());
+        };
+
+        let mut changed = watcher.changed();
+
+        // Any shader may include a changed include file, so all of them are recompiled.
+        if changed.iter().any(|name| name.ends_with(INCLUDE_EXTENSION)) {
+            changed.extend(self.data.shaders.keys().cloned());
+        }
+
+        changed.retain(|name| self.data.shaders.contains_key(name));
+        if changed.is_empty() {
+            return Ok(());
+        }
+
+        // Compile
+
+        let mut shaders = self.data.shaders.clone();
+        for name in &changed {
+            match compile_shader(&self.data.shader_directory.join(name)) {
+                Ok(code) => shaders.insert(name.clone(), code),
+                Err(e) => {
+                    error!("{}", e);
+                    return Ok(());
+                }
+            };
+        }
+
+        // Pipelines
+
+        let modes = self
+            .data
+            .pipelines
+            .iter()
+            .filter(|(_, p)| p.builder.uses_any(&changed))
+            .map(|(m, _)| *m)
+            .collect::<Vec<_>>();
+
+        if modes.is_empty() {
+            self.data.shaders = shaders;
+            return Ok(());
+        }
+
+        self.device.device_wait_idle().unwrap();
+
+        // Either every affected pipeline is rebuilt or none are, so all pipelines use the same shaders.
+        let old_shaders = mem::replace(&mut self.data.shaders, shaders);
+        let mut pipelines = vec![];
+        for mode in modes {
+            match self.data.pipelines[&mode].builder.build(&self.device, &self.data) {
+                Ok(pipeline) => pipelines.push((mode, pipeline)),
+                Err(e) => {
+                    error!("Failed to rebuild {:?} pipeline with reloaded shaders: {}", mode, e);
+                    pipelines.iter().for_each(|(_, p)| p.destroy(&self.device));
+                    self.data.shaders = old_shaders;
+                    return Ok(());
+                }
+            }
+        }
+
+        for (mode, pipeline) in pipelines {
+            if let Some(old) = self.data.pipelines.insert(mode, pipeline) {
+                old.destroy(&self.device);
+            }
+        }
+
+        info!("Reloaded shaders: {:?}", changed);
+
+        Ok(())
+    }
+
     // Recreates the swapchain for our Vulkan app.
+    //
+    // The old swapchain is handed over to the new one and retired instead of waiting for the device to be idle.
     #[rustfmt::skip]
     pub unsafe fn recreate_swapchain(&mut self, window: &Window) -> Result<()> {
-        self.device.device_wait_idle().unwrap();
-        self.destroy_swapchain();
-        create_swapchain(window, &self.instance, &self.device, &mut self.data).unwrap();
+        let format = self.data.swapchain_format;
+        let color_space = self.data.swapchain_color_space;
+        let retired = retire_swapchain(&mut self.data);
+        let old_swapchain = retired.swapchain;
+        self.data.retired_swapchains.push(retired);
+        create_swapchain(window, &self.instance, &self.device, &mut self.data, old_swapchain)?;
         create_swapchain_image_views(&self.device, &mut self.data).unwrap();
-        create_render_pass(&self.instance, &self.device, &mut self.data).unwrap();
-        create_pipeline(&self.device, &mut self.data).unwrap();
+        // The render pass (and the pipelines created for it) only depend on the swapchain format and color space, not its
+        // extent.
+        if self.data.swapchain_format != format || self.data.swapchain_color_space != color_space {
+            self.device.device_wait_idle().unwrap();
+            self.destroy_pipeline();
+            create_render_pass(&self.instance, &self.device, &mut self.data).unwrap();
+            create_pipelines(&self.device, &mut self.data, self.camera.near, self.camera.far).unwrap();
+        }
         create_color_objects(&self.instance, &self.device, &mut self.data).unwrap();
         create_depth_objects(&self.instance, &self.device, &mut self.data).unwrap();
         create_framebuffers(&self.device, &mut self.data).unwrap();
         create_uniform_buffers(&self.instance, &self.device, &mut self.data).unwrap();
         create_descriptor_pool(&self.device, &mut self.data).unwrap();
         create_descriptor_sets(&self.device, &mut self.data).unwrap();
+        create_image_command_pools(&self.device, &mut self.data).unwrap();
         create_command_buffers(&self.device, &mut self.data).unwrap();
-        self.data.images_in_flight.resize(self.data.swapchain_images.len(), vk::Fence::null());
+        // The fences of the old swapchain images don't apply to the new ones (whose number may differ).
+        self.data.images_in_flight = vec![vk::Fence::null(); self.data.swapchain_images.len()];
         Ok(())
     }
 
+    // Destroys the retired swapchains whose frames have finished, now that an in flight fence has been waited on.
+    pub unsafe fn destroy_retired_swapchains(&mut self, fence: vk::Fence) {
+        let retired = mem::take(&mut self.data.retired_swapchains);
+        for mut swapchain in retired {
+            swapchain.pending_fences.retain(|f| *f != fence);
+            if swapchain.pending_fences.is_empty() {
+                swapchain.destroy(&self.device);
+            } else {
+                self.data.retired_swapchains.push(swapchain);
+            }
+        }
+    }
+
     // Destroys our Vulkan app.
     #[rustfmt::skip]
     pub unsafe fn destroy(&mut self) {
@@ -499,19 +855,32 @@ impl App {
         self.data.in_flight_fences.iter().for_each(|f| self.device.destroy_fence(*f, None));
         self.data.render_finished_semaphores.iter().for_each(|s| self.device.destroy_semaphore(*s, None));
         self.data.image_available_semaphores.iter().for_each(|s| self.device.destroy_semaphore(*s, None));
-        self.data.command_pools.iter().for_each(|p| self.device.destroy_command_pool(*p, None));
         self.device.free_memory(self.data.index_buffer_memory, None);
         self.device.destroy_buffer(self.data.index_buffer, None);
         self.device.free_memory(self.data.vertex_buffer_memory, None);
         self.device.destroy_buffer(self.data.vertex_buffer, None);
         self.device.destroy_sampler(self.data.texture_sampler, None);
-        self.device.destroy_image_view(self.data.texture_image_view, None);
-        self.device.free_memory(self.data.texture_image_memory, None);
-        self.device.destroy_image(self.data.texture_image, None);
+        self.data.textures.iter().for_each(|t| self.device.destroy_image_view(t.image_view, None));
+        self.data.textures.iter().for_each(|t| self.device.free_memory(t.image_memory, None));
+        self.data.textures.iter().for_each(|t| self.device.destroy_image(t.image, None));
+        self.device.destroy_command_pool(self.data.transfer_command_pool, None);
         self.device.destroy_command_pool(self.data.command_pool, None);
+        self.destroy_pipeline();
         self.device.destroy_descriptor_set_layout(self.data.descriptor_set_layout, None);
+
+        // Headless runs (e.g., golden image comparisons) leave the cache file untouched.
+        if !self.data.headless {
+            if let Err(e) = save_pipeline_cache(&self.device, &self.data) {
+                warn!("Failed to save pipeline cache `{}`: {}", self.data.pipeline_cache_path.display(), e);
+            }
+        }
+
+        self.device.destroy_pipeline_cache(self.data.pipeline_cache, None);
         self.device.destroy_device(None);
-        self.instance.destroy_surface_khr(self.data.surface, None);
+
+        if !self.data.headless {
+            self.instance.destroy_surface_khr(self.data.surface, None);
+        }
 
         if VALIDATION_ENABLED {
             self.instance.destroy_debug_utils_messenger_ext(self.data.messenger, None);
@@ -520,12 +889,85 @@ impl App {
         self.instance.destroy_instance(None);
     }
 
-    // Destroys the parts of our Vulkan app related to the swapchain.
+    // Switches the vsync mode, recreating the swapchain with the matching present mode.
+    //
+    // If the swapchain can't be recreated, the previous vsync mode is kept.
+    pub unsafe fn set_vsync(&mut self, window: &Window, vsync: VSync) -> Result<()> {
+        let previous = mem::replace(&mut self.data.vsync, vsync);
+        if let Err(e) = self.recreate_swapchain(window) {
+            // The old swapchain is retired even if creating the new one failed, so it has to be recreated as well.
+            self.data.vsync = previous;
+            self.recreate_swapchain(window)?;
+            return Err(e);
+        }
+
+        Ok(())
+    }
+
+    // Switches to the next vsync mode (on, off, adaptive).
+    pub unsafe fn cycle_vsync(&mut self, window: &Window) -> Result<()> {
+        let vsync = match self.data.vsync {
+            VSync::On => VSync::Off,
+            VSync::Off => VSync::Adaptive,
+            VSync::Adaptive => VSync::On,
+        };
+
+        self.set_vsync(window, vsync)
+    }
+
+    // Switches the number of samples rendered per pixel, recreating everything that depends on it.
+    //
+    // The new pipelines are built before the old ones are destroyed, so the old sample count is kept if that fails.
     #[rustfmt::skip]
-    pub unsafe fn destroy_swapchain(&mut self) {
-        self.device.destroy_descriptor_pool(self.data.descriptor_pool, None);
-        self.data.uniform_buffers_memory.iter().for_each(|m| self.device.free_memory(*m, None));
-        self.data.uniform_buffers.iter().for_each(|b| self.device.destroy_buffer(*b, None));
+    pub unsafe fn set_msaa_samples(&mut self, samples: vk::SampleCountFlags) -> Result<()> {
+        if samples == self.data.msaa_samples {
+            return Ok(());
+        }
+
+        self.device.device_wait_idle().unwrap();
+        let old_samples = mem::replace(&mut self.data.msaa_samples, samples);
+        let old_render_pass = self.data.render_pass;
+        let old_pipelines = mem::take(&mut self.data.pipelines);
+        create_render_pass(&self.instance, &self.device, &mut self.data).unwrap();
+        if let Err(e) = create_pipelines(&self.device, &mut self.data, self.camera.near, self.camera.far) {
+            self.destroy_pipeline();
+            self.data.msaa_samples = old_samples;
+            self.data.render_pass = old_render_pass;
+            self.data.pipelines = old_pipelines;
+            return Err(e);
+        }
+        old_pipelines.into_values().for_each(|p| p.destroy(&self.device));
+        self.device.destroy_render_pass(old_render_pass, None);
+        self.destroy_attachments();
+        create_color_objects(&self.instance, &self.device, &mut self.data).unwrap();
+        create_depth_objects(&self.instance, &self.device, &mut self.data).unwrap();
+        create_framebuffers(&self.device, &mut self.data).unwrap();
+
+        if !self.data.pipelines.contains_key(&self.debug_mode) {
+            self.debug_mode = DebugMode::Lit;
+        }
+
+        info!("MSAA: {}x", samples.bits());
+
+        Ok(())
+    }
+
+    // Switches to the next sample count supported by the device (wrapping around to no multisampling).
+    pub unsafe fn cycle_msaa_samples(&mut self) -> Result<()> {
+        let counts = &self.data.msaa_sample_counts;
+        let index = counts.iter().position(|c| *c == self.data.msaa_samples).unwrap_or(0);
+        self.set_msaa_samples(counts[(index + 1) % counts.len()])
+    }
+
+    // Destroys the render pass and the pipelines created for it.
+    pub unsafe fn destroy_pipeline(&mut self) {
+        self.data.pipelines.drain().for_each(|(_, p)| p.destroy(&self.device));
+        self.device.destroy_render_pass(self.data.render_pass, None);
+    }
+
+    // Destroys the color and depth attachments and the framebuffers using them.
+    #[rustfmt::skip]
+    pub unsafe fn destroy_attachments(&mut self) {
         self.device.destroy_image_view(self.data.depth_image_view, None);
         self.device.free_memory(self.data.depth_image_memory, None);
         self.device.destroy_image(self.data.depth_image, None);
@@ -533,11 +975,18 @@ impl App {
         self.device.free_memory(self.data.color_image_memory, None);
         self.device.destroy_image(self.data.color_image, None);
         self.data.framebuffers.iter().for_each(|f| self.device.destroy_framebuffer(*f, None));
-        self.device.destroy_pipeline(self.data.pipeline, None);
-        self.device.destroy_pipeline_layout(self.data.pipeline_layout, None);
-        self.device.destroy_render_pass(self.data.render_pass, None);
-        self.data.swapchain_image_views.iter().for_each(|v| self.device.destroy_image_view(*v, None));
-        self.device.destroy_swapchain_khr(self.data.swapchain, None);
+    }
+
+    // Destroys the parts of our Vulkan app related to the swapchain (including any retired swapchains).
+    #[rustfmt::skip]
+    pub unsafe fn destroy_swapchain(&mut self) {
+        self.data.retired_swapchains.drain(..).for_each(|s| s.destroy(&self.device));
+        retire_swapchain(&mut self.data).destroy(&self.device);
+
+        if self.data.headless {
+            self.data.swapchain_images.iter().for_each(|i| self.device.destroy_image(*i, None));
+            self.device.free_memory(self.data.offscreen_image_memory, None);
+        }
     }
 }
 
@@ -546,28 +995,62 @@ impl App {
 pub struct AppData {
     // Debug
     pub messenger: vk::DebugUtilsMessengerEXT,
+    // Instance
+    // The Vulkan version the instance was created for (see `vk::make_version`).
+    pub api_version: u32,
     // Surface
+    pub headless: bool,
     pub surface: vk::SurfaceKHR,
     // Physical Device / Logical Device
     pub physical_device: vk::PhysicalDevice,
+    // The sample counts supported for our attachments, in ascending order.
+    pub msaa_sample_counts: Vec<vk::SampleCountFlags>,
     pub msaa_samples: vk::SampleCountFlags,
+    // Whether our texture format can be sampled (and blitted) with linear filtering.
+    pub texture_linear_filtering: bool,
+    // The minimum fraction of samples shaded per fragment by the pipelines using sample shading.
+    pub sample_shading: Option<f32>,
+    pub features: DeviceFeatures,
+    pub queue_families: QueueFamilyIndices,
     pub graphics_queue: vk::Queue,
     pub present_queue: vk::Queue,
+    pub transfer_queue: vk::Queue,
     // Swapchain
     pub swapchain_format: vk::Format,
     pub swapchain_extent: vk::Extent2D,
     pub swapchain: vk::SwapchainKHR,
+    pub vsync: VSync,
+    pub present_mode: vk::PresentModeKHR,
+    // The requested output color space and the one the swapchain was created for (SDR if the requested one isn't
+    // supported), and the luminance (in nits) of SDR white on HDR outputs.
+    pub output_color_space: OutputColorSpace,
+    pub swapchain_color_space: OutputColorSpace,
+    pub paper_white: f32,
     pub swapchain_images: Vec<vk::Image>,
     pub swapchain_image_views: Vec<vk::ImageView>,
+    // The replaced swapchains that are destroyed once their frames have finished.
+    pub retired_swapchains: Vec<RetiredSwapchain>,
+    // Offscreen
+    pub offscreen_image_memory: vk::DeviceMemory,
     // Pipeline
     pub render_pass: vk::RenderPass,
     pub descriptor_set_layout: vk::DescriptorSetLayout,
-    pub pipeline_layout: vk::PipelineLayout,
-    pub pipeline: vk::Pipeline,
+    // The descriptors declared by the shaders that the descriptor set layout was created from.
+    pub descriptor_bindings: Vec<DescriptorBinding>,
+    // The pipeline variant for each debug mode supported by the device.
+    pub pipelines: HashMap<DebugMode, Pipeline>,
+    pub pipeline_cache: vk::PipelineCache,
+    pub pipeline_cache_path: PathBuf,
+    // Shaders
+    pub shader_directory: PathBuf,
+    // The SPIR-V of the last successfully compiled version of each shader, by file name.
+    pub shaders: HashMap<String, Vec<u8>>,
     // Framebuffers
     pub framebuffers: Vec<vk::Framebuffer>,
     // Command Pool
     pub command_pool: vk::CommandPool,
+    // The pool for single time commands on the transfer queue.
+    pub transfer_command_pool: vk::CommandPool,
     // Color
     pub color_image: vk::Image,
     pub color_image_memory: vk::DeviceMemory,
@@ -576,25 +1059,29 @@ pub struct AppData {
     pub depth_image: vk::Image,
     pub depth_image_memory: vk::DeviceMemory,
     pub depth_image_view: vk::ImageView,
+    pub depth_format: vk::Format,
     // Texture
-    pub mip_levels: u32,
-    pub texture_image: vk::Image,
-    pub texture_image_memory: vk::DeviceMemory,
-    pub texture_image_view: vk::ImageView,
+    pub textures: Vec<Texture>,
     pub texture_sampler: vk::Sampler,
     // Model
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
+    pub materials: Vec<Material>,
+    pub submeshes: Vec<Submesh>,
+    pub decoded_images: Vec<DecodedImage>,
     // Buffers
     pub vertex_buffer: vk::Buffer,
     pub vertex_buffer_memory: vk::DeviceMemory,
     pub index_buffer: vk::Buffer,
     pub index_buffer_memory: vk::DeviceMemory,
+    // The type of the indices in the index buffer (`UINT16` when every vertex can be indexed with 16 bits).
+    pub index_type: vk::IndexType,
     pub uniform_buffers: Vec<vk::Buffer>,
     pub uniform_buffers_memory: Vec<vk::DeviceMemory>,
     // Descriptors
     pub descriptor_pool: vk::DescriptorPool,
-    pub descriptor_sets: Vec<vk::DescriptorSet>,
+    // The descriptor sets for each swapchain image and material.
+    pub descriptor_sets: Vec<Vec<vk::DescriptorSet>>,
     // Command Buffers
     pub command_pools: Vec<vk::CommandPool>,
     pub command_buffers: Vec<vk::CommandBuffer>,
@@ -1,8 +1,12 @@
 use super::{
+    color_space::OutputColorSpace,
+    config::VSync,
     shared_images::create_image_view,
     structures::{AppData, QueueFamilyIndices, SwapchainSupport},
 };
 use anyhow::Result;
+use log::*;
+use std::mem;
 use vulkanalia::{prelude::v1_0::*, vk::KhrSwapchainExtension};
 use winit::window::Window;
 
@@ -15,18 +19,34 @@ pub unsafe fn create_swapchain(
     instance: &Instance,
     device: &Device,
     data: &mut AppData,
+    old_swapchain: vk::SwapchainKHR,
 ) -> Result<()> {
     // Image
 
     let indices = QueueFamilyIndices::get(instance, data, data.physical_device).unwrap();
     let support = SwapchainSupport::get(instance, data, data.physical_device).unwrap();
 
-    let surface_format = get_swapchain_surface_format(&support.formats);
-    let present_mode = get_swapchain_present_mode(&support.present_modes);
+    let (surface_format, color_space) = get_swapchain_surface_format(&support.formats, data.output_color_space);
+    let present_mode = get_swapchain_present_mode(&support.present_modes, data.vsync);
     let extent = get_swapchain_extent(window, support.capabilities);
 
-    data.swapchain_format = surface_format.format;
-    data.swapchain_extent = extent;
+    if old_swapchain.is_null() || present_mode != data.present_mode {
+        info!("Present mode: {:?} (vsync {:?}).", present_mode, data.vsync);
+    }
+
+    if old_swapchain.is_null() {
+        if color_space != data.output_color_space {
+            warn!(
+                "The surface doesn't support {:?} output, falling back to SDR.",
+                data.output_color_space
+            );
+        }
+
+        info!(
+            "Output color space: {:?} ({:?}, {:?}).",
+            color_space, surface_format.format, surface_format.color_space
+        );
+    }
 
     let mut image_count = support.capabilities.min_image_count + 1;
     if support.capabilities.max_image_count != 0 && image_count > support.capabilities.max_image_count {
@@ -58,9 +78,13 @@ pub unsafe fn create_swapchain(
         .composite_alpha(vk::CompositeAlphaFlagsKHR::OPAQUE)
         .present_mode(present_mode)
         .clipped(true)
-        .old_swapchain(vk::SwapchainKHR::null());
+        .old_swapchain(old_swapchain);
 
-    data.swapchain = device.create_swapchain_khr(&info, None).unwrap();
+    data.swapchain = device.create_swapchain_khr(&info, None)?;
+    data.swapchain_format = surface_format.format;
+    data.swapchain_color_space = color_space;
+    data.swapchain_extent = extent;
+    data.present_mode = present_mode;
 
     // Images
 
@@ -69,19 +93,35 @@ pub unsafe fn create_swapchain(
     Ok(())
 }
 
-fn get_swapchain_surface_format(formats: &[vk::SurfaceFormatKHR]) -> vk::SurfaceFormatKHR {
-    formats
-        .iter()
-        .cloned()
-        .find(|f| f.format == vk::Format::B8G8R8A8_SRGB && f.color_space == vk::ColorSpaceKHR::SRGB_NONLINEAR)
-        .unwrap_or_else(|| formats[0])
+// Picks a surface format for the requested output color space, falling back to SDR if the surface doesn't offer one.
+fn get_swapchain_surface_format(
+    formats: &[vk::SurfaceFormatKHR],
+    color_space: OutputColorSpace,
+) -> (vk::SurfaceFormatKHR, OutputColorSpace) {
+    if let Some(format) = color_space.find_surface_format(formats) {
+        return (format, color_space);
+    }
+
+    let format = OutputColorSpace::Sdr
+        .find_surface_format(formats)
+        .unwrap_or_else(|| formats[0]);
+
+    (format, OutputColorSpace::Sdr)
 }
 
-fn get_swapchain_present_mode(present_modes: &[vk::PresentModeKHR]) -> vk::PresentModeKHR {
-    present_modes
+// Picks the first supported present mode for a vsync mode, falling back to FIFO (which is always supported).
+fn get_swapchain_present_mode(present_modes: &[vk::PresentModeKHR], vsync: VSync) -> vk::PresentModeKHR {
+    let preferred: &[vk::PresentModeKHR] = match vsync {
+        VSync::On => &[],
+        // MAILBOX doesn't tear, but still never waits for the vertical blank when rendering.
+        VSync::Off => &[vk::PresentModeKHR::IMMEDIATE, vk::PresentModeKHR::MAILBOX],
+        VSync::Adaptive => &[vk::PresentModeKHR::FIFO_RELAXED],
+    };
+
+    preferred
         .iter()
         .cloned()
-        .find(|m| *m == vk::PresentModeKHR::MAILBOX)
+        .find(|m| present_modes.contains(m))
         .unwrap_or(vk::PresentModeKHR::FIFO)
 }
 
@@ -113,3 +153,77 @@ pub unsafe fn create_swapchain_image_views(device: &Device, data: &mut AppData)
 
     Ok(())
 }
+
+//================================================
+// Retired Swapchain
+//================================================
+
+// A swapchain that has been replaced and the resources created for it, which are kept alive until the frames that were
+// in flight when it was replaced have finished.
+#[derive(Clone, Debug, Default)]
+pub struct RetiredSwapchain {
+    pub swapchain: vk::SwapchainKHR,
+    pub image_views: Vec<vk::ImageView>,
+    pub framebuffers: Vec<vk::Framebuffer>,
+    pub color_image: vk::Image,
+    pub color_image_memory: vk::DeviceMemory,
+    pub color_image_view: vk::ImageView,
+    pub depth_image: vk::Image,
+    pub depth_image_memory: vk::DeviceMemory,
+    pub depth_image_view: vk::ImageView,
+    pub uniform_buffers: Vec<vk::Buffer>,
+    pub uniform_buffers_memory: Vec<vk::DeviceMemory>,
+    pub descriptor_pool: vk::DescriptorPool,
+    pub command_pools: Vec<vk::CommandPool>,
+    // The in flight fences that still have to be waited on before the resources can be destroyed.
+    pub pending_fences: Vec<vk::Fence>,
+}
+
+impl RetiredSwapchain {
+    #[rustfmt::skip]
+    pub unsafe fn destroy(&self, device: &Device) {
+        self.command_pools.iter().for_each(|p| device.destroy_command_pool(*p, None));
+        device.destroy_descriptor_pool(self.descriptor_pool, None);
+        self.uniform_buffers_memory.iter().for_each(|m| device.free_memory(*m, None));
+        self.uniform_buffers.iter().for_each(|b| device.destroy_buffer(*b, None));
+        device.destroy_image_view(self.depth_image_view, None);
+        device.free_memory(self.depth_image_memory, None);
+        device.destroy_image(self.depth_image, None);
+        device.destroy_image_view(self.color_image_view, None);
+        device.free_memory(self.color_image_memory, None);
+        device.destroy_image(self.color_image, None);
+        self.framebuffers.iter().for_each(|f| device.destroy_framebuffer(*f, None));
+        self.image_views.iter().for_each(|v| device.destroy_image_view(*v, None));
+
+        // There is no swapchain (or swapchain extension) when rendering offscreen.
+        if !self.swapchain.is_null() {
+            device.destroy_swapchain_khr(self.swapchain, None);
+        }
+    }
+}
+
+// Moves the swapchain and the resources created for it (per swapchain image or for its extent) out of our app.
+//
+// Every in flight fence is pending since any of them may guard a frame that still uses these resources.
+pub fn retire_swapchain(data: &mut AppData) -> RetiredSwapchain {
+    data.descriptor_sets.clear();
+    data.command_buffers.clear();
+    data.secondary_command_buffers.clear();
+
+    RetiredSwapchain {
+        swapchain: mem::take(&mut data.swapchain),
+        image_views: mem::take(&mut data.swapchain_image_views),
+        framebuffers: mem::take(&mut data.framebuffers),
+        color_image: mem::take(&mut data.color_image),
+        color_image_memory: mem::take(&mut data.color_image_memory),
+        color_image_view: mem::take(&mut data.color_image_view),
+        depth_image: mem::take(&mut data.depth_image),
+        depth_image_memory: mem::take(&mut data.depth_image_memory),
+        depth_image_view: mem::take(&mut data.depth_image_view),
+        uniform_buffers: mem::take(&mut data.uniform_buffers),
+        uniform_buffers_memory: mem::take(&mut data.uniform_buffers_memory),
+        descriptor_pool: mem::take(&mut data.descriptor_pool),
+        command_pools: mem::take(&mut data.command_pools),
+        pending_fences: data.in_flight_fences.clone(),
+    }
+}
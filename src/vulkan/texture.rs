@@ -1,36 +1,118 @@
 use super::{
     shared_buffers::create_buffer,
-    shared_images::{copy_buffer_to_image, create_image, create_image_view, transition_image_layout},
-    shared_other::{begin_single_time_commands, end_single_time_commands},
-    structures::AppData,
+    shared_images::{
+        copy_buffer_to_image, copy_buffer_to_mip_levels, create_image, create_image_view,
+        record_image_layout_transition, transition_image_layout,
+    },
+    shared_other::{
+        begin_queue_commands, begin_single_time_commands, end_single_time_commands, end_transfer_commands, QueueFamily,
+    },
+    structures::{AppData, Texture, TextureSource},
 };
 use anyhow::{anyhow, Result};
-use std::{fs::File, ptr::copy_nonoverlapping as memcpy};
+use log::*;
+use std::{collections::HashMap, fs::File, path::Path, ptr::copy_nonoverlapping as memcpy};
 use vulkanalia::prelude::v1_0::*;
 
 //================================================
 // Texture
 //================================================
 
-pub unsafe fn create_texture_image(instance: &Instance, device: &Device, data: &mut AppData) -> Result<()> {
-    // Load
+// The pixel of the texture used by untextured materials (and materials whose texture failed to load).
+const WHITE_PIXEL: [u8; 4] = [255; 4];
 
-    let image = File::open("src/resources/viking_room.png").unwrap();
-
-    let decoder = png::Decoder::new(image);
-    let mut reader = decoder.read_info().unwrap();
+// Loads the diffuse texture of every material, sharing textures between materials that use the same source.
+pub unsafe fn create_textures(
+    instance: &Instance,
+    device: &Device,
+    data: &mut AppData,
+    default_texture: &Path,
+) -> Result<()> {
+    let decoded_images = std::mem::take(&mut data.decoded_images);
+    let mut loaded = HashMap::new();
+
+    for index in 0..data.materials.len() {
+        let source = data.materials[index]
+            .diffuse_texture
+            .clone()
+            .unwrap_or_else(|| TextureSource::File(default_texture.to_path_buf()));
+
+        let texture = if let Some(texture) = loaded.get(&source) {
+            *texture
+        } else {
+            let mut texture = match &source {
+                TextureSource::File(path) => match load_png_rgba8(path) {
+                    Ok((pixels, width, height)) => {
+                        create_texture_image_from_pixels(instance, device, data, &pixels, width, height)?
+                    }
+                    // Unlike the configured texture, a material texture that can't be loaded is not fatal.
+                    Err(e) if data.materials[index].diffuse_texture.is_some() => {
+                        warn!("{} (using a white texture instead)", e);
+                        create_texture_image_from_pixels(instance, device, data, &WHITE_PIXEL, 1, 1)?
+                    }
+                    Err(e) => return Err(e),
+                },
+                TextureSource::Decoded(image) => {
+                    let image = &decoded_images[*image];
+                    create_texture_image_from_pixels(instance, device, data, &image.pixels, image.width, image.height)?
+                }
+                TextureSource::White => create_texture_image_from_pixels(instance, device, data, &WHITE_PIXEL, 1, 1)?,
+            };
+
+            create_texture_image_view(device, &mut texture).unwrap();
+            data.textures.push(texture);
+            loaded.insert(source, data.textures.len() - 1);
+            data.textures.len() - 1
+        };
+
+        data.materials[index].texture = texture;
+    }
 
-    let mut pixels = vec![0; reader.info().raw_bytes()];
-    reader.next_frame(&mut pixels).unwrap();
+    Ok(())
+}
 
-    let size = reader.info().raw_bytes() as u64;
-    let (width, height) = reader.info().size();
-    data.mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+pub unsafe fn create_texture_image(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    path: &Path,
+) -> Result<Texture> {
+    let (pixels, width, height) = load_png_rgba8(path)?;
+    create_texture_image_from_pixels(instance, device, data, &pixels, width, height)
+}
 
-    if width != 1024 || height != 1024 || reader.info().color_type != png::ColorType::Rgba {
-        panic!("Invalid texture image.");
+// Creates a texture image from tightly packed RGBA8 pixels.
+pub unsafe fn create_texture_image_from_pixels(
+    instance: &Instance,
+    device: &Device,
+    data: &AppData,
+    pixels: &[u8],
+    width: u32,
+    height: u32,
+) -> Result<Texture> {
+    let mip_levels = (width.max(height) as f32).log2().floor() as u32 + 1;
+
+    // Mipmaps are blitted from the full resolution image if the format supports linear filtering, otherwise they are
+    // generated on the CPU and uploaded along with it.
+    let blit_mipmaps = data.texture_linear_filtering;
+
+    let mut mipmaps: Vec<(Vec<u8>, u32, u32)> = vec![];
+    if !blit_mipmaps {
+        for _ in 1..mip_levels {
+            let (pixels, width, height) = mipmaps
+                .last()
+                .map_or((pixels, width, height), |(p, w, h)| (&p[..], *w, *h));
+            let mipmap = downsample_rgba8(pixels, width, height);
+            mipmaps.push(mipmap);
+        }
     }
 
+    let levels = std::iter::once((pixels, width, height))
+        .chain(mipmaps.iter().map(|(p, w, h)| (&p[..], *w, *h)))
+        .collect::<Vec<_>>();
+
+    let size = levels.iter().map(|(p, ..)| p.len()).sum::<usize>() as u64;
+
     // Create (staging)
 
     let (staging_buffer, staging_buffer_memory) = create_buffer(
@@ -49,7 +131,13 @@ pub unsafe fn create_texture_image(instance: &Instance, device: &Device, data: &
         .map_memory(staging_buffer_memory, 0, size, vk::MemoryMapFlags::empty())
         .unwrap();
 
-    memcpy(pixels.as_ptr(), memory.cast(), pixels.len());
+    let mut offset = 0;
+    let mut regions = vec![];
+    for (pixels, width, height) in &levels {
+        memcpy(pixels.as_ptr(), memory.cast::<u8>().add(offset), pixels.len());
+        regions.push((offset as vk::DeviceSize, *width, *height));
+        offset += pixels.len();
+    }
 
     device.unmap_memory(staging_buffer_memory);
 
@@ -61,7 +149,7 @@ pub unsafe fn create_texture_image(instance: &Instance, device: &Device, data: &
         data,
         width,
         height,
-        data.mip_levels,
+        mip_levels,
         vk::SampleCountFlags::_1,
         vk::Format::R8G8B8A8_SRGB,
         vk::ImageTiling::OPTIMAL,
@@ -70,23 +158,34 @@ pub unsafe fn create_texture_image(instance: &Instance, device: &Device, data: &
     )
     .unwrap();
 
-    data.texture_image = texture_image;
-    data.texture_image_memory = texture_image_memory;
-
     // Transition + Copy (image)
 
-    transition_image_layout(
+    let command_buffer = begin_queue_commands(device, data, QueueFamily::Transfer).unwrap();
+
+    record_image_layout_transition(
         device,
-        data,
-        data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
+        command_buffer,
+        texture_image,
         vk::ImageLayout::UNDEFINED,
         vk::ImageLayout::TRANSFER_DST_OPTIMAL,
-        data.mip_levels,
+        mip_levels,
     )
     .unwrap();
 
-    copy_buffer_to_image(device, data, staging_buffer, data.texture_image, width, height).unwrap();
+    if blit_mipmaps {
+        copy_buffer_to_image(device, command_buffer, staging_buffer, texture_image, width, height);
+    } else {
+        copy_buffer_to_mip_levels(device, command_buffer, staging_buffer, texture_image, &regions);
+    }
+
+    // The mipmaps are generated and the image is sampled on the graphics queue.
+    end_transfer_commands(
+        device,
+        data,
+        command_buffer,
+        &[],
+        &[(texture_image, vk::ImageLayout::TRANSFER_DST_OPTIMAL, mip_levels)],
+    )?;
 
     // Cleanup
 
@@ -95,41 +194,83 @@ pub unsafe fn create_texture_image(instance: &Instance, device: &Device, data: &
 
     // Mipmaps
 
-    generate_mipmaps(
-        instance,
-        device,
-        data,
-        data.texture_image,
-        vk::Format::R8G8B8A8_SRGB,
-        width,
-        height,
-        data.mip_levels,
-    )
-    .unwrap();
+    if blit_mipmaps {
+        generate_mipmaps(device, data, texture_image, width, height, mip_levels).unwrap();
+    } else {
+        transition_image_layout(
+            device,
+            data,
+            texture_image,
+            vk::Format::R8G8B8A8_SRGB,
+            vk::ImageLayout::TRANSFER_DST_OPTIMAL,
+            vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            mip_levels,
+        )
+        .unwrap();
+    }
 
-    Ok(())
+    Ok(Texture {
+        mip_levels,
+        image: texture_image,
+        image_memory: texture_image_memory,
+        ..Default::default()
+    })
+}
+
+// Decodes a PNG of any color type and bit depth into tightly packed RGBA8 pixels.
+pub fn load_png_rgba8(path: impl AsRef<Path>) -> Result<(Vec<u8>, u32, u32)> {
+    let path = path.as_ref();
+    let image = File::open(path).map_err(|e| anyhow!("Failed to open texture `{}`: {}", path.display(), e))?;
+
+    // Expand indexed and low bit depth images and strip 16-bit channels down to 8 bits.
+    let mut decoder = png::Decoder::new(image);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+
+    let mut reader = decoder
+        .read_info()
+        .map_err(|e| anyhow!("Failed to decode texture `{}`: {}", path.display(), e))?;
+
+    let mut buffer = vec![0; reader.output_buffer_size()];
+    let info = reader
+        .next_frame(&mut buffer)
+        .map_err(|e| anyhow!("Failed to decode texture `{}`: {}", path.display(), e))?;
+    buffer.truncate(info.buffer_size());
+
+    if info.bit_depth != png::BitDepth::Eight {
+        return Err(anyhow!(
+            "Unsupported texture bit depth in `{}`: {:?}",
+            path.display(),
+            info.bit_depth
+        ));
+    }
+
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buffer,
+        png::ColorType::Rgb => expand_to_rgba8(&buffer, |p| [p[0], p[1], p[2], 255], 3),
+        png::ColorType::GrayscaleAlpha => expand_to_rgba8(&buffer, |p| [p[0], p[0], p[0], p[1]], 2),
+        png::ColorType::Grayscale => expand_to_rgba8(&buffer, |p| [p[0], p[0], p[0], 255], 1),
+        png::ColorType::Indexed => {
+            return Err(anyhow!("Unexpanded indexed texture in `{}`.", path.display()));
+        }
+    };
+
+    Ok((pixels, info.width, info.height))
 }
 
+// Converts tightly packed pixels with `channels` channels each into RGBA8 pixels.
+pub fn expand_to_rgba8(pixels: &[u8], convert: impl Fn(&[u8]) -> [u8; 4], channels: usize) -> Vec<u8> {
+    pixels.chunks_exact(channels).flat_map(convert).collect()
+}
+
+// Blits each mip level from the previous one (requires linear filtering support for the image format).
 unsafe fn generate_mipmaps(
-    instance: &Instance,
     device: &Device,
     data: &AppData,
     image: vk::Image,
-    format: vk::Format,
     width: u32,
     height: u32,
     mip_levels: u32,
 ) -> Result<()> {
-    // Support
-
-    if !instance
-        .get_physical_device_format_properties(data.physical_device, format)
-        .optimal_tiling_features
-        .contains(vk::FormatFeatureFlags::SAMPLED_IMAGE_FILTER_LINEAR)
-    {
-        return Err(anyhow!("Texture image format does not support linear blitting!"));
-    }
-
     // Mipmaps
 
     let command_buffer = begin_single_time_commands(device, data).unwrap();
@@ -253,38 +394,152 @@ unsafe fn generate_mipmaps(
     Ok(())
 }
 
-pub unsafe fn create_texture_image_view(device: &Device, data: &mut AppData) -> Result<()> {
-    data.texture_image_view = create_image_view(
+// Halves the size of an RGBA8 (sRGB) image by averaging 2x2 blocks of pixels in linear space.
+pub fn downsample_rgba8(pixels: &[u8], width: u32, height: u32) -> (Vec<u8>, u32, u32) {
+    let to_linear = (0..256)
+        .map(|c| {
+            let c = c as f32 / 255.0;
+            if c <= 0.04045 {
+                c / 12.92
+            } else {
+                ((c + 0.055) / 1.055).powf(2.4)
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let to_srgb = |c: f32| {
+        let c = if c <= 0.0031308 {
+            c * 12.92
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        };
+        (c * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+
+    let (mip_width, mip_height) = ((width / 2).max(1), (height / 2).max(1));
+    let mut mip = Vec::with_capacity((mip_width * mip_height * 4) as usize);
+    for y in 0..mip_height {
+        for x in 0..mip_width {
+            // Odd sizes repeat the last row or column.
+            let xs = [(x * 2).min(width - 1), (x * 2 + 1).min(width - 1)];
+            let ys = [(y * 2).min(height - 1), (y * 2 + 1).min(height - 1)];
+            let texels = ys
+                .iter()
+                .flat_map(|y| xs.iter().map(move |x| ((y * width + x) * 4) as usize))
+                .collect::<Vec<_>>();
+
+            for channel in 0..3 {
+                let sum = texels
+                    .iter()
+                    .map(|t| to_linear[pixels[t + channel] as usize])
+                    .sum::<f32>();
+                mip.push(to_srgb(sum / 4.0));
+            }
+
+            let alpha = texels.iter().map(|t| pixels[t + 3] as u32).sum::<u32>();
+            mip.push(((alpha + 2) / 4) as u8);
+        }
+    }
+
+    (mip, mip_width, mip_height)
+}
+
+pub unsafe fn create_texture_image_view(device: &Device, texture: &mut Texture) -> Result<()> {
+    texture.image_view = create_image_view(
         device,
-        data.texture_image,
+        texture.image,
         vk::Format::R8G8B8A8_SRGB,
         vk::ImageAspectFlags::COLOR,
-        data.mip_levels,
+        texture.mip_levels,
     )
     .unwrap();
 
     Ok(())
 }
 
-pub unsafe fn create_texture_sampler(device: &Device, data: &mut AppData) -> Result<()> {
+pub unsafe fn create_texture_sampler(instance: &Instance, device: &Device, data: &mut AppData) -> Result<()> {
+    let mip_levels = data.textures.iter().map(|t| t.mip_levels).max().unwrap_or(1);
+
+    // Linear filtering is optional for our texture format.
+    let (filter, mipmap_mode) = if data.texture_linear_filtering {
+        (vk::Filter::LINEAR, vk::SamplerMipmapMode::LINEAR)
+    } else {
+        (vk::Filter::NEAREST, vk::SamplerMipmapMode::NEAREST)
+    };
+
+    // Anisotropic filtering is optional.
+    let max_anisotropy = if data.features.sampler_anisotropy {
+        let properties = instance.get_physical_device_properties(data.physical_device);
+        properties.limits.max_sampler_anisotropy.min(16.0)
+    } else {
+        1.0
+    };
+
     let info = vk::SamplerCreateInfo::builder()
-        .mag_filter(vk::Filter::LINEAR)
-        .min_filter(vk::Filter::LINEAR)
+        .mag_filter(filter)
+        .min_filter(filter)
         .address_mode_u(vk::SamplerAddressMode::REPEAT)
         .address_mode_v(vk::SamplerAddressMode::REPEAT)
         .address_mode_w(vk::SamplerAddressMode::REPEAT)
-        .anisotropy_enable(true)
-        .max_anisotropy(16.0)
+        .anisotropy_enable(data.features.sampler_anisotropy)
+        .max_anisotropy(max_anisotropy)
         .border_color(vk::BorderColor::INT_OPAQUE_BLACK)
         .unnormalized_coordinates(false)
         .compare_enable(false)
         .compare_op(vk::CompareOp::ALWAYS)
-        .mipmap_mode(vk::SamplerMipmapMode::LINEAR)
+        .mipmap_mode(mipmap_mode)
         .min_lod(0.0)
-        .max_lod(data.mip_levels as f32)
+        .max_lod(mip_levels as f32)
         .mip_lod_bias(0.0);
 
     data.texture_sampler = device.create_sampler(&info, None).unwrap();
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn downsample_even_size() {
+        let pixels = [[10, 20, 30, 40]; 16].concat();
+        let (mip, width, height) = downsample_rgba8(&pixels, 4, 4);
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(mip, [[10, 20, 30, 40]; 4].concat());
+    }
+
+    #[test]
+    fn downsample_odd_size() {
+        // Odd sizes round down, dropping the last column or row.
+        let pixels = [[0, 0, 0, 255], [0, 0, 0, 255], [255, 255, 255, 255]].concat();
+        let (mip, width, height) = downsample_rgba8(&pixels, 3, 1);
+        assert_eq!((width, height), (1, 1));
+        assert_eq!(mip, [0, 0, 0, 255]);
+
+        let (mip, width, height) = downsample_rgba8(&[0; 5 * 3 * 4], 5, 3);
+        assert_eq!((width, height), (2, 1));
+        assert_eq!(mip.len(), 2 * 4);
+    }
+
+    #[test]
+    fn downsample_single_column() {
+        // A 1xN image keeps its width and repeats its only column.
+        let pixels = [[255, 0, 0, 255], [255, 0, 0, 255], [0, 0, 255, 0], [0, 0, 255, 0]].concat();
+        let (mip, width, height) = downsample_rgba8(&pixels, 1, 4);
+        assert_eq!((width, height), (1, 2));
+        assert_eq!(mip, [[255, 0, 0, 255], [0, 0, 255, 0]].concat());
+
+        let (mip, width, height) = downsample_rgba8(&[1, 2, 3, 4], 1, 1);
+        assert_eq!((width, height), (1, 1));
+        assert_eq!(mip, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn downsample_averages_in_linear_space() {
+        // Black and white average to 50% linear intensity, which is 188 in sRGB (not 128), while alpha is linear.
+        let pixels = [[0, 0, 0, 0], [255, 255, 255, 255]].concat();
+        let (mip, ..) = downsample_rgba8(&pixels, 2, 1);
+        assert_eq!(mip, [188, 188, 188, 128]);
+    }
+}
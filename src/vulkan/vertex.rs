@@ -0,0 +1,320 @@
+use super::constants::{Mat4, Vec2, Vec3, Vec4};
+use anyhow::{anyhow, Result};
+use std::mem::size_of;
+use vulkanalia::prelude::v1_0::*;
+
+//================================================
+// Vertex Layout
+//================================================
+
+// A type that can be read by a vertex shader input.
+pub trait VertexFormat {
+    // The format of each location occupied by the type.
+    const FORMAT: vk::Format;
+    // The number of consecutive locations occupied by the type (e.g., one per column of a matrix).
+    const LOCATIONS: u32 = 1;
+}
+
+impl VertexFormat for f32 {
+    const FORMAT: vk::Format = vk::Format::R32_SFLOAT;
+}
+
+impl VertexFormat for Vec2 {
+    const FORMAT: vk::Format = vk::Format::R32G32_SFLOAT;
+}
+
+impl VertexFormat for Vec3 {
+    const FORMAT: vk::Format = vk::Format::R32G32B32_SFLOAT;
+}
+
+impl VertexFormat for Vec4 {
+    const FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+}
+
+impl VertexFormat for Mat4 {
+    const FORMAT: vk::Format = vk::Format::R32G32B32A32_SFLOAT;
+    const LOCATIONS: u32 = 4;
+}
+
+impl VertexFormat for u32 {
+    const FORMAT: vk::Format = vk::Format::R32_UINT;
+}
+
+impl VertexFormat for [u32; 4] {
+    const FORMAT: vk::Format = vk::Format::R32G32B32A32_UINT;
+}
+
+impl VertexFormat for [u16; 4] {
+    const FORMAT: vk::Format = vk::Format::R16G16B16A16_UINT;
+}
+
+// A field of a vertex type read by a vertex shader input.
+#[derive(Copy, Clone, Debug)]
+pub struct VertexAttribute {
+    pub name: &'static str,
+    pub offset: u32,
+    pub size: u32,
+    pub format: vk::Format,
+    pub locations: u32,
+}
+
+impl VertexAttribute {
+    // Describes the field of `V` with the given name and offset, deriving its format from the field's type.
+    //
+    // The accessor is never called; it only ties the format to the type of the field at compile time.
+    pub fn new<V, T: VertexFormat>(name: &'static str, offset: usize, _field: fn(&V) -> &T) -> Self {
+        Self {
+            name,
+            offset: offset as u32,
+            size: size_of::<T>() as u32,
+            format: T::FORMAT,
+            locations: T::LOCATIONS,
+        }
+    }
+}
+
+// A `#[repr(C)]` type that can be bound as a vertex buffer, usually implemented with `vertex_layout!`.
+pub trait VertexLayout: Copy + Sized {
+    // Whether the vertex buffer advances per vertex or per instance.
+    const INPUT_RATE: vk::VertexInputRate = vk::VertexInputRate::VERTEX;
+
+    fn attributes() -> Vec<VertexAttribute>;
+
+    fn binding_description(binding: u32) -> vk::VertexInputBindingDescription {
+        vk::VertexInputBindingDescription::builder()
+            .binding(binding)
+            .stride(size_of::<Self>() as u32)
+            .input_rate(Self::INPUT_RATE)
+            .build()
+    }
+
+    fn attribute_descriptions(binding: u32, first_location: u32) -> Vec<vk::VertexInputAttributeDescription> {
+        let mut location = first_location;
+        let mut descriptions = vec![];
+        for attribute in Self::attributes() {
+            let column_size = attribute.size / attribute.locations;
+            for column in 0..attribute.locations {
+                descriptions.push(
+                    vk::VertexInputAttributeDescription::builder()
+                        .binding(binding)
+                        .location(location)
+                        .format(attribute.format)
+                        .offset(attribute.offset + column * column_size)
+                        .build(),
+                );
+                location += 1;
+            }
+        }
+        descriptions
+    }
+}
+
+// Implements `VertexLayout` for a `#[repr(C)]` struct from the names of its fields, in shader location order.
+//
+// vertex_layout!(Vertex { pos, color });
+// vertex_layout!(instance InstanceData { model });
+macro_rules! vertex_layout {
+    (instance $type:ty { $($field:ident),* $(,)? }) => {
+        $crate::vulkan::vertex::vertex_layout!(@impl $type, vulkanalia::vk::VertexInputRate::INSTANCE, $($field),*);
+    };
+    ($type:ty { $($field:ident),* $(,)? }) => {
+        $crate::vulkan::vertex::vertex_layout!(@impl $type, vulkanalia::vk::VertexInputRate::VERTEX, $($field),*);
+    };
+    (@impl $type:ty, $rate:expr, $($field:ident),*) => {
+        impl $crate::vulkan::vertex::VertexLayout for $type {
+            const INPUT_RATE: vulkanalia::vk::VertexInputRate = $rate;
+
+            fn attributes() -> Vec<$crate::vulkan::vertex::VertexAttribute> {
+                vec![$(
+                    $crate::vulkan::vertex::VertexAttribute::new(
+                        stringify!($field),
+                        std::mem::offset_of!($type, $field),
+                        |v: &$type| &v.$field,
+                    )
+                ),*]
+            }
+        }
+    };
+}
+
+pub(crate) use vertex_layout;
+
+// The vertex buffer bindings and attributes of a pipeline.
+#[derive(Clone, Debug, Default)]
+pub struct VertexInput {
+    pub bindings: Vec<vk::VertexInputBindingDescription>,
+    pub attributes: Vec<vk::VertexInputAttributeDescription>,
+    // The stride and attributes of the vertex type bound to each binding.
+    layouts: Vec<(u32, Vec<VertexAttribute>)>,
+}
+
+impl VertexInput {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Adds a binding for `V`, assigning its attributes the locations after the previous bindings.
+    pub fn with<V: VertexLayout>(mut self) -> Self {
+        let binding = self.bindings.len() as u32;
+        let first_location = self.attributes.iter().map(|a| a.location + 1).max().unwrap_or(0);
+
+        self.bindings.push(V::binding_description(binding));
+        self.attributes
+            .extend(V::attribute_descriptions(binding, first_location));
+        self.layouts.push((size_of::<V>() as u32, V::attributes()));
+
+        self
+    }
+
+    // Checks that the attributes of each binding lie within its stride and don't overlap.
+    pub fn validate(&self) -> Result<()> {
+        for (stride, attributes) in &self.layouts {
+            let mut ranges = attributes
+                .iter()
+                .map(|a| (a.offset, a.offset + a.size, a.name))
+                .collect::<Vec<_>>();
+            ranges.sort();
+
+            for (start, end, name) in &ranges {
+                if end > stride {
+                    return Err(anyhow!(
+                        "Vertex attribute `{}` ({}..{}) exceeds the vertex stride ({}).",
+                        name,
+                        start,
+                        end,
+                        stride
+                    ));
+                }
+            }
+
+            for pair in ranges.windows(2) {
+                if pair[0].1 > pair[1].0 {
+                    return Err(anyhow!(
+                        "Vertex attributes `{}` and `{}` overlap.",
+                        pair[0].2,
+                        pair[1].2
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    struct SkinnedVertex {
+        pos: Vec3,
+        normal: Vec3,
+        tex_coord: Vec2,
+        joints: [u16; 4],
+        weights: Vec4,
+    }
+
+    vertex_layout!(SkinnedVertex {
+        pos,
+        normal,
+        tex_coord,
+        joints,
+        weights
+    });
+
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    struct InstanceData {
+        model: Mat4,
+        color: Vec4,
+    }
+
+    vertex_layout!(instance InstanceData { model, color });
+
+    // A layout whose attributes overlap and exceed its stride.
+    #[repr(C)]
+    #[derive(Copy, Clone, Debug)]
+    struct InvalidVertex {
+        pos: Vec3,
+    }
+
+    impl VertexLayout for InvalidVertex {
+        fn attributes() -> Vec<VertexAttribute> {
+            vec![
+                VertexAttribute::new("pos", 0, |v: &Self| &v.pos),
+                VertexAttribute::new("tex_coord", 8, |v: &Self| &v.pos),
+            ]
+        }
+    }
+
+    fn offsets(descriptions: &[vk::VertexInputAttributeDescription]) -> Vec<(u32, u32, vk::Format)> {
+        descriptions.iter().map(|d| (d.location, d.offset, d.format)).collect()
+    }
+
+    #[test]
+    fn vertex_offsets() {
+        let binding = SkinnedVertex::binding_description(0);
+        assert_eq!(binding.stride, 56);
+        assert_eq!(binding.input_rate, vk::VertexInputRate::VERTEX);
+
+        assert_eq!(
+            offsets(&SkinnedVertex::attribute_descriptions(0, 0)),
+            [
+                (0, 0, vk::Format::R32G32B32_SFLOAT),
+                (1, 12, vk::Format::R32G32B32_SFLOAT),
+                (2, 24, vk::Format::R32G32_SFLOAT),
+                (3, 32, vk::Format::R16G16B16A16_UINT),
+                (4, 40, vk::Format::R32G32B32A32_SFLOAT),
+            ]
+        );
+    }
+
+    #[test]
+    fn instance_offsets() {
+        let input = VertexInput::new().with::<SkinnedVertex>().with::<InstanceData>();
+        input.validate().unwrap();
+
+        assert_eq!(input.bindings.len(), 2);
+        assert_eq!(input.bindings[1].stride, 80);
+        assert_eq!(input.bindings[1].input_rate, vk::VertexInputRate::INSTANCE);
+
+        // Each column of the matrix takes a location, after the locations of the first binding.
+        let instance = input
+            .attributes
+            .iter()
+            .filter(|a| a.binding == 1)
+            .copied()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            offsets(&instance),
+            [
+                (5, 0, vk::Format::R32G32B32A32_SFLOAT),
+                (6, 16, vk::Format::R32G32B32A32_SFLOAT),
+                (7, 32, vk::Format::R32G32B32A32_SFLOAT),
+                (8, 48, vk::Format::R32G32B32A32_SFLOAT),
+                (9, 64, vk::Format::R32G32B32A32_SFLOAT),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_invalid_layouts() {
+        let input = VertexInput::new().with::<InvalidVertex>();
+        assert!(input.validate().is_err());
+
+        // Each problem on its own.
+        let overlapping = VertexInput {
+            layouts: vec![(24, InvalidVertex::attributes())],
+            ..VertexInput::new()
+        };
+        assert!(overlapping.validate().is_err());
+
+        let exceeding = VertexInput {
+            layouts: vec![(8, InvalidVertex::attributes()[..1].to_vec())],
+            ..VertexInput::new()
+        };
+        assert!(exceeding.validate().is_err());
+    }
+}